@@ -0,0 +1,137 @@
+//! Examples from `traits-advanced.md`.
+//!
+//! The regions between `// ANCHOR:` comments are included in the chapter.
+
+// ANCHOR: some_trait
+// Generic trait definition
+pub trait SomeTrait {
+    fn some_function(&mut self);
+}
+
+// Implementation for a built-in array iterator
+impl<T, const N: usize> SomeTrait for core::array::IntoIter<T, N>
+where
+    T: std::fmt::Debug,
+{
+    fn some_function(&mut self) {
+        for item in self {
+            println!("Processing item: {:?}", item);
+        }
+    }
+}
+// ANCHOR_END: some_trait
+
+// ANCHOR: number_range
+// Custom collection type
+pub struct NumberRange {
+    start: i32,
+    end: i32,
+}
+
+// Custom iterator type
+pub struct NumberRangeIterator {
+    current: i32,
+    end: i32,
+}
+
+// Implementation for creating an iterator
+impl NumberRange {
+    pub fn new(start: i32, end: i32) -> Self {
+        NumberRange { start, end }
+    }
+
+    pub fn iter(&self) -> NumberRangeIterator {
+        NumberRangeIterator {
+            current: self.start,
+            end: self.end,
+        }
+    }
+}
+
+// Implementation of the Iterator trait
+impl Iterator for NumberRangeIterator {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current <= self.end {
+            let current = self.current;
+            self.current += 1;
+            Some(current)
+        } else {
+            None
+        }
+    }
+}
+// ANCHOR_END: number_range
+
+// ANCHOR: collection
+use std::ops::{Index, IndexMut};
+
+// Custom collection type
+#[derive(Debug)]
+pub struct Collection {
+    data: Vec<i32>,
+}
+
+impl Collection {
+    pub fn new(data: Vec<i32>) -> Self {
+        Collection { data }
+    }
+}
+
+// Implementing Index trait for immutable access
+impl Index<usize> for Collection {
+    type Output = i32;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+// Implementing IndexMut trait for mutable access
+impl IndexMut<usize> for Collection {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.data[index]
+    }
+}
+// ANCHOR_END: collection
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consumes_array_iterators() {
+        let mut iter = [1, 2, 3].into_iter();
+        iter.some_function();
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn number_range_is_inclusive() {
+        let numbers = NumberRange::new(1, 5);
+        assert_eq!(numbers.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(numbers.iter().sum::<i32>(), 15);
+    }
+
+    #[test]
+    fn empty_number_range() {
+        assert_eq!(NumberRange::new(3, 2).iter().next(), None);
+    }
+
+    #[test]
+    fn collection_indexing() {
+        let mut collection = Collection::new(vec![1, 2, 3, 4, 5]);
+        assert_eq!(collection[2], 3);
+        collection[2] = 10;
+        assert_eq!(collection[2], 10);
+        assert_eq!(collection.data, vec![1, 2, 10, 4, 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn collection_index_out_of_bounds_panics() {
+        let collection = Collection::new(vec![1]);
+        let _ = collection[1];
+    }
+}
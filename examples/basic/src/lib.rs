@@ -0,0 +1,168 @@
+//! Examples from `traits-basic.md`.
+//!
+//! The regions between `// ANCHOR:` comments are included in the chapter.
+
+// ANCHOR: required_implementation
+pub trait TraitName {
+    fn function_name(&self) -> String;
+}
+
+// Example Implementation
+pub struct StructName {
+    pub struct_field: String,
+}
+
+impl TraitName for StructName {
+    fn function_name(&self) -> String {
+        self.struct_field.clone()
+    }
+}
+// ANCHOR_END: required_implementation
+
+/// The same trait with a default implementation of `function_name`.
+pub mod default_implementation {
+    // ANCHOR: default_implementation
+    pub trait TraitName {
+        fn function_name(&self) -> String {
+            String::from("(Default implementation...)")
+        }
+    }
+
+    pub struct StructName {
+        pub struct_field: String,
+    }
+
+    // We can implement the trait without defining the function
+    impl TraitName for StructName {}
+    // ANCHOR_END: default_implementation
+}
+
+// ANCHOR: function_a
+pub fn function_a(item: &impl TraitName) {
+    println!("This type implement the trait! {}", item.function_name());
+}
+// ANCHOR_END: function_a
+
+// ANCHOR: function_b
+pub fn function_b<T: TraitName>(item: &T) {
+    println!("This type implement the trait! {}", item.function_name());
+}
+// ANCHOR_END: function_b
+
+// ANCHOR: function_c
+// Allows different types that implement TraitName
+pub fn function_c(item1: &impl TraitName, item2: &impl TraitName) {
+    println!(
+        "This types implement the trait! {} {}",
+        item1.function_name(),
+        item2.function_name()
+    );
+}
+// ANCHOR_END: function_c
+
+// ANCHOR: function_d
+// Forces both parameters to be the same type
+pub fn function_d<T: TraitName>(item1: &T, item2: &T) {
+    println!(
+        "This types implement the trait! {} {}",
+        item1.function_name(),
+        item2.function_name()
+    );
+}
+// ANCHOR_END: function_d
+
+// ANCHOR: other_trait
+// A second trait to combine with TraitName
+pub trait OtherTrait {
+    fn other_function(&self) -> String;
+}
+
+impl OtherTrait for StructName {
+    fn other_function(&self) -> String {
+        format!("other {}", self.struct_field)
+    }
+}
+// ANCHOR_END: other_trait
+
+// ANCHOR: function_e
+pub fn function_e(item: &(impl TraitName + OtherTrait)) {
+    println!("{} and {}", item.function_name(), item.other_function());
+}
+// ANCHOR_END: function_e
+
+// ANCHOR: function_f
+pub fn function_f<T: TraitName + OtherTrait>(item: &T) {
+    println!("{} and {}", item.function_name(), item.other_function());
+}
+// ANCHOR_END: function_f
+
+// ANCHOR: where_clause
+use std::fmt::{Debug, Display};
+
+pub fn some_function<T, U>(t: &T, u: &U) -> i32
+where
+    T: Display + Clone,
+    U: Clone + Debug,
+{
+    // Function body
+    println!("{} {:?}", t.clone(), u.clone());
+    0
+}
+// ANCHOR_END: where_clause
+
+// ANCHOR: return_impl_trait
+pub fn some_function_a() -> impl TraitName {
+    StructName {
+        struct_field: String::from("returned value"),
+    }
+}
+// ANCHOR_END: return_impl_trait
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(value: &str) -> StructName {
+        StructName {
+            struct_field: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn required_implementation_uses_the_field() {
+        assert_eq!(item("value").function_name(), "value");
+    }
+
+    #[test]
+    fn default_implementation_is_used_when_not_overridden() {
+        use default_implementation::TraitName as _;
+
+        let item = default_implementation::StructName {
+            struct_field: String::from("ignored"),
+        };
+        assert_eq!(item.struct_field, "ignored");
+        assert_eq!(item.function_name(), "(Default implementation...)");
+    }
+
+    #[test]
+    fn trait_bounds_accept_implementing_types() {
+        let (a, b) = (item("a"), item("b"));
+        function_a(&a);
+        function_b(&a);
+        function_c(&a, &some_function_a());
+        function_d(&a, &b);
+        function_e(&a);
+        function_f(&b);
+        assert_eq!(a.other_function(), "other a");
+    }
+
+    #[test]
+    fn where_clause_bounds() {
+        assert_eq!(some_function(&"text", &vec![1, 2]), 0);
+    }
+
+    #[test]
+    fn returns_an_opaque_implementation() {
+        assert_eq!(some_function_a().function_name(), "returned value");
+    }
+}
@@ -0,0 +1,223 @@
+//! `#[derive(PipelineError)]` for the examples in `traits-functional`.
+//!
+//! Written against `proc_macro` alone, so the workspace still needs no crates
+//! from the registry.
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+/// Implements `From` for an error enum, once for the field of each variant,
+/// so that `?` and `bind_into` convert the error of every step into it.
+///
+/// ```
+/// use traits_functional_derive::PipelineError;
+///
+/// #[derive(Debug, PipelineError)]
+/// pub enum LoadError {
+///     Number(std::num::ParseIntError),
+///     Message(String),
+/// }
+///
+/// let error: LoadError = "x".parse::<i32>().unwrap_err().into();
+/// assert!(matches!(error, LoadError::Number(_)));
+/// assert!(matches!(LoadError::from("lost".to_string()), LoadError::Message(_)));
+/// ```
+///
+/// Every variant holds exactly one field, the error it converts from. The
+/// messages of the errors below are compared with the expected ones in
+/// `tests/ui/pipeline_error` of `traits-functional`:
+///
+/// ```compile_fail
+/// // error: variant `Unknown` must hold the error it converts from, as in `Unknown(SomeError)`
+/// #[derive(traits_functional_derive::PipelineError)]
+/// enum LoadError {
+///     Unknown,
+/// }
+/// ```
+///
+/// ```compile_fail
+/// // error: `Parse` and `Read` both hold `String`, but only one variant can be built from it
+/// #[derive(traits_functional_derive::PipelineError)]
+/// enum LoadError {
+///     Parse(String),
+///     Read(String),
+/// }
+/// ```
+///
+/// ```compile_fail
+/// // error: `PipelineError` can only be derived for an enum
+/// #[derive(traits_functional_derive::PipelineError)]
+/// struct LoadError(String);
+/// ```
+#[proc_macro_derive(PipelineError)]
+pub fn derive_pipeline_error(input: TokenStream) -> TokenStream {
+    match from_impls(input) {
+        Ok(impls) => impls.parse().expect("generated code is valid Rust"),
+        Err(error) => error.into_compile_error(),
+    }
+}
+
+/// Why the impls cannot be generated, and the tokens to point at.
+struct Error {
+    message: String,
+    span: Span,
+}
+
+impl Error {
+    fn new(message: impl Into<String>, span: Span) -> Self {
+        Error {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// `::core::compile_error!("message");` with every token at `span`, so
+    /// that rustc underlines the offending tokens rather than the derive.
+    fn into_compile_error(self) -> TokenStream {
+        let punct = |c, spacing| TokenTree::Punct(Punct::new(c, spacing));
+        let ident = |name| TokenTree::Ident(Ident::new(name, self.span));
+        let message = TokenTree::Literal(Literal::string(&self.message));
+        let tokens = [
+            punct(':', Spacing::Joint),
+            punct(':', Spacing::Alone),
+            ident("core"),
+            punct(':', Spacing::Joint),
+            punct(':', Spacing::Alone),
+            ident("compile_error"),
+            punct('!', Spacing::Alone),
+            TokenTree::Group(Group::new(Delimiter::Parenthesis, message.into())),
+            punct(';', Spacing::Alone),
+        ];
+        tokens
+            .into_iter()
+            .map(|mut token| {
+                token.set_span(self.span);
+                token
+            })
+            .collect()
+    }
+}
+
+/// The `From` impls as source code, or why they cannot be generated.
+fn from_impls(input: TokenStream) -> Result<String, Error> {
+    let mut tokens = input.into_iter();
+    // Attributes and the visibility come before the keyword
+    let name = loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(keyword)) if keyword.to_string() == "enum" => {
+                match tokens.next() {
+                    Some(TokenTree::Ident(name)) => break name,
+                    _ => return Err(Error::new("expected the name of the enum", keyword.span())),
+                }
+            }
+            Some(TokenTree::Ident(keyword))
+                if keyword.to_string() == "struct" || keyword.to_string() == "union" =>
+            {
+                return Err(Error::new(
+                    "`PipelineError` can only be derived for an enum",
+                    keyword.span(),
+                ));
+            }
+            Some(_) => {}
+            None => return Err(Error::new("expected an enum", Span::call_site())),
+        }
+    };
+    let body = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+        token => {
+            return Err(Error::new(
+                format!("`PipelineError` cannot be derived for `{name}`, which has generics"),
+                token.map_or(name.span(), |token| token.span()),
+            ))
+        }
+    };
+    let mut fields: Vec<(Ident, String)> = Vec::new();
+    for variant in split(body.into_iter().collect(), ',') {
+        let (variant, field) = variant_field(variant)?;
+        // Two impls of `From<T>` would conflict; name both variants instead
+        if let Some((first, _)) = fields.iter().find(|(_, known)| *known == field) {
+            return Err(Error::new(
+                format!("`{first}` and `{variant}` both hold `{field}`, but only one variant can be built from it"),
+                variant.span(),
+            ));
+        }
+        fields.push((variant, field));
+    }
+    let mut impls = String::new();
+    for (variant, field) in fields {
+        impls.push_str(&format!(
+            "impl ::core::convert::From<{field}> for {name} {{\n    \
+                 fn from(error: {field}) -> Self {{\n        \
+                     {name}::{variant}(error)\n    \
+                 }}\n\
+             }}\n"
+        ));
+    }
+    Ok(impls)
+}
+
+/// The name of a variant and the type of its only field.
+fn variant_field(tokens: Vec<TokenTree>) -> Result<(Ident, String), Error> {
+    let mut tokens = tokens.into_iter().peekable();
+    // Doc comments and other attributes
+    while matches!(tokens.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '#') {
+        tokens.next();
+        tokens.next();
+    }
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(name)) => name,
+        token => {
+            let span = token.map_or(Span::call_site(), |token| token.span());
+            return Err(Error::new("expected the name of a variant", span));
+        }
+    };
+    let one_field = || {
+        Error::new(
+            format!(
+                "variant `{name}` must hold the error it converts from, as in `{name}(SomeError)`"
+            ),
+            name.span(),
+        )
+    };
+    let fields = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Group(group)), None) if group.delimiter() == Delimiter::Parenthesis => {
+            split(group.stream().into_iter().collect(), ',')
+        }
+        _ => return Err(one_field()),
+    };
+    match <[_; 1]>::try_from(fields) {
+        Ok([field]) => {
+            let field = field.into_iter().collect::<TokenStream>().to_string();
+            Ok((name, field))
+        }
+        Err(_) => Err(one_field()),
+    }
+}
+
+/// Splits on `separator` outside of angle brackets; groups are single
+/// tokens already. Empty parts, as after a trailing comma, are dropped.
+fn split(tokens: Vec<TokenTree>, separator: char) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0usize;
+    let mut previous = ' ';
+    for token in tokens {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                // `->` in a function type is not a closing bracket
+                '>' if previous != '-' => depth = depth.saturating_sub(1),
+                c if c == separator && depth == 0 => {
+                    parts.push(Vec::new());
+                    previous = c;
+                    continue;
+                }
+                _ => {}
+            }
+            previous = punct.as_char();
+        } else {
+            previous = ' ';
+        }
+        parts.last_mut().unwrap().push(token);
+    }
+    parts.retain(|part| !part.is_empty());
+    parts
+}
@@ -0,0 +1,327 @@
+//! The railway for steps that are `async fn(T) -> Result<U, E>`.
+//!
+//! No async runtime is needed: [`block_on`] is a minimal executor that
+//! parks the current thread until the future can make progress.
+
+use crate::pipeline::StepError;
+
+// ANCHOR: and_then_async
+use std::future::Future;
+
+pub trait AsyncMonadicResult<T, E> {
+    /// Awaits `f` on the success value; an error skips `f` and is kept.
+    fn and_then_async<U, F, Fut>(self, f: F) -> impl Future<Output = Result<U, E>>
+    where
+        F: FnOnce(T) -> Fut,
+        Fut: Future<Output = Result<U, E>>;
+}
+
+impl<T, E> AsyncMonadicResult<T, E> for Result<T, E> {
+    async fn and_then_async<U, F, Fut>(self, f: F) -> Result<U, E>
+    where
+        F: FnOnce(T) -> Fut,
+        Fut: Future<Output = Result<U, E>>,
+    {
+        match self {
+            Ok(value) => f(value).await,
+            Err(error) => Err(error), // Early return on error
+        }
+    }
+}
+// ANCHOR_END: and_then_async
+
+// ANCHOR: async_pipeline
+use std::pin::Pin;
+use std::sync::Arc;
+
+type AsyncStepFn<T, E> =
+    Arc<dyn Fn(T) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send>> + Send + Sync>;
+
+/// A [`Pipeline`](crate::pipeline::Pipeline) of async steps.
+pub struct AsyncPipeline<T, E> {
+    steps: Vec<(&'static str, AsyncStepFn<T, E>)>,
+}
+
+impl<T, E> AsyncPipeline<T, E> {
+    pub fn new() -> Self {
+        AsyncPipeline { steps: Vec::new() }
+    }
+
+    /// Adds an async step after the others. Each run calls `f` and awaits
+    /// its future, boxed so that steps of different futures can be stored,
+    /// before the next step starts.
+    pub fn step<F, Fut>(mut self, name: &'static str, f: F) -> Self
+    where
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+    {
+        self.steps
+            .push((name, Arc::new(move |value| Box::pin(f(value)))));
+        self
+    }
+
+    /// Awaits every step in order. The first failing step stops the run,
+    /// and its name is returned with the error.
+    pub async fn run(&self, input: T) -> Result<T, StepError<E>> {
+        let mut value = input;
+        for (name, step) in &self.steps {
+            value = step(value)
+                .await
+                .map_err(|error| StepError { step: name, error })?;
+        }
+        Ok(value)
+    }
+
+    /// Runs the pipeline on every input, with at most `limit` inputs in
+    /// flight at once. The results are in the order of the inputs, and each
+    /// input stops at its own first failing step.
+    pub async fn run_all<I>(&self, inputs: I, limit: usize) -> Vec<Result<T, StepError<E>>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut results = Vec::new();
+        self.fan_out(inputs, limit, |index, result| {
+            results.push((index, result));
+            true
+        })
+        .await;
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Like `run_all`, but the whole batch fails with the first error: no
+    /// new input is started and the ones still in flight are dropped.
+    pub async fn try_run_all<I>(&self, inputs: I, limit: usize) -> Result<Vec<T>, StepError<E>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut values = Vec::new();
+        let mut failure = None;
+        self.fan_out(inputs, limit, |index, result| match result {
+            Ok(value) => {
+                values.push((index, value));
+                true
+            }
+            Err(error) => {
+                failure = Some(error);
+                false
+            }
+        })
+        .await;
+        if let Some(error) = failure {
+            return Err(error);
+        }
+        values.sort_by_key(|(index, _)| *index);
+        Ok(values.into_iter().map(|(_, value)| value).collect())
+    }
+
+    /// Polls up to `limit` runs at a time, handing each finished one to
+    /// `done` until it returns `false` or the inputs run out.
+    async fn fan_out<I, D>(&self, inputs: I, limit: usize, mut done: D)
+    where
+        I: IntoIterator<Item = T>,
+        D: FnMut(usize, Result<T, StepError<E>>) -> bool,
+    {
+        let mut inputs = inputs.into_iter().enumerate();
+        let mut running = Vec::new();
+        std::future::poll_fn(|cx| loop {
+            while running.len() < limit.max(1) {
+                match inputs.next() {
+                    Some((index, input)) => running.push((index, Box::pin(self.run(input)))),
+                    None => break,
+                }
+            }
+            if running.is_empty() {
+                return Poll::Ready(());
+            }
+            let mut finished = Vec::new();
+            running.retain_mut(|(index, run)| match run.as_mut().poll(cx) {
+                Poll::Ready(result) => {
+                    finished.push((*index, result));
+                    false
+                }
+                Poll::Pending => true,
+            });
+            if finished.is_empty() {
+                return Poll::Pending;
+            }
+            for (index, result) in finished {
+                if !done(index, result) {
+                    return Poll::Ready(());
+                }
+            }
+        })
+        .await
+    }
+}
+
+impl<T, E> Default for AsyncPipeline<T, E> {
+    fn default() -> Self {
+        AsyncPipeline::new()
+    }
+}
+
+impl<T, E> Clone for AsyncPipeline<T, E> {
+    fn clone(&self) -> Self {
+        AsyncPipeline {
+            steps: self.steps.clone(),
+        }
+    }
+}
+// ANCHOR_END: async_pipeline
+
+// ANCHOR: block_on
+use std::pin::pin;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// Runs `future` to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Gives other futures a turn, standing in for waiting on I/O.
+pub async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+// ANCHOR_END: block_on
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data, EncryptedData, ProcessError, ProcessResult};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    async fn validate(data: EncryptedData) -> ProcessResult {
+        yield_now().await;
+        crate::validate_data(data)
+    }
+
+    async fn encrypt(data: EncryptedData) -> ProcessResult {
+        yield_now().await;
+        crate::apply_encryption(data)
+    }
+
+    fn encryption() -> AsyncPipeline<EncryptedData, ProcessError> {
+        AsyncPipeline::new()
+            .step("validate", validate)
+            .step("encrypt", encrypt)
+    }
+
+    #[test]
+    fn and_then_async_changes_types_and_skips_after_errors() {
+        let length = block_on(async {
+            Ok::<_, ProcessError>(data("secret", 123))
+                .and_then_async(validate)
+                .await
+                .and_then_async(|data| async move { Ok(data.content.len()) })
+                .await
+        });
+        assert_eq!(length.unwrap(), 6);
+
+        let called = AtomicUsize::new(0);
+        let result = block_on(async {
+            Ok(data("", 123))
+                .and_then_async(validate)
+                .await
+                .and_then_async(|data| {
+                    called.fetch_add(1, Ordering::SeqCst);
+                    encrypt(data)
+                })
+                .await
+        });
+        assert!(matches!(result, Err(ProcessError::InvalidInput)));
+        assert_eq!(called.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn pipeline_reports_the_failing_step() {
+        let pipeline = encryption();
+        let ok = block_on(pipeline.run(data("secret", 123))).unwrap();
+        assert_eq!(ok.content, "encrypted_secret");
+        let error = block_on(pipeline.run(data("", 123))).unwrap_err();
+        assert_eq!(error.step, "validate");
+    }
+
+    #[test]
+    fn run_all_limits_the_inputs_in_flight() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (now, max) = (in_flight.clone(), most.clone());
+        let pipeline = AsyncPipeline::new().step("slow", move |x: i32| {
+            let (now, max) = (now.clone(), max.clone());
+            async move {
+                let running = now.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(running, Ordering::SeqCst);
+                for _ in 0..3 {
+                    yield_now().await;
+                }
+                now.fetch_sub(1, Ordering::SeqCst);
+                match x {
+                    3 => Err("three"),
+                    x => Ok(x * 10),
+                }
+            }
+        });
+        let results = block_on(pipeline.run_all(1..=5, 2));
+        let values: Vec<_> = results
+            .iter()
+            .map(|result| result.as_ref().map_err(|error| error.error))
+            .collect();
+        assert_eq!(
+            values,
+            vec![Ok(&10), Ok(&20), Err("three"), Ok(&40), Ok(&50)]
+        );
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn try_run_all_stops_starting_inputs_after_a_failure() {
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let log = started.clone();
+        let pipeline = AsyncPipeline::new().step("check", move |x: i32| {
+            log.lock().unwrap().push(x);
+            async move {
+                yield_now().await;
+                if x == 2 {
+                    Err(ProcessError::ValidationFailed)
+                } else {
+                    Ok(x)
+                }
+            }
+        });
+        let error = block_on(pipeline.try_run_all(1..=5, 1)).unwrap_err();
+        assert!(matches!(error.error, ProcessError::ValidationFailed));
+        assert_eq!(*started.lock().unwrap(), vec![1, 2]);
+        assert_eq!(
+            block_on(pipeline.try_run_all([4, 5], 3)).unwrap(),
+            vec![4, 5]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Errors that remember which step failed and what it was doing.
+
+use crate::{EncryptedData, ProcessError, ProcessResult};
+
+// ANCHOR: context
+use std::error::Error;
+use std::fmt;
+
+/// One frame of context around an error: the step that failed, what it was
+/// doing, and the error it got, if any. Frames stack: the source of a frame
+/// may be another frame.
+#[derive(Debug)]
+pub struct ContextError {
+    pub step: &'static str,
+    pub message: String,
+    source: Option<Box<dyn Error + Send + Sync + 'static>>,
+}
+
+impl ContextError {
+    /// A frame for a failure without an underlying error.
+    pub fn new(step: &'static str, message: impl Into<String>) -> Self {
+        ContextError {
+            step,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// This frame and every error below it, one per line.
+    pub fn report(&self) -> String {
+        let mut report = format!("error: {self}");
+        let mut source = self.source();
+        if source.is_some() {
+            report.push_str("\ncaused by:");
+        }
+        let mut depth = 0;
+        while let Some(error) = source {
+            report.push_str(&format!("\n  {depth}: {error}"));
+            source = error.source();
+            depth += 1;
+        }
+        report
+    }
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step `{}`: {}", self.step, self.message)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|error| error as &(dyn Error + 'static))
+    }
+}
+
+pub trait ResultContext<T> {
+    /// Wraps the error in a frame naming the step and what it was doing.
+    fn context(self, step: &'static str, message: impl Into<String>) -> Result<T, ContextError>;
+
+    /// Like `context`, but the message is only built when there is an error.
+    fn with_context<F, M>(self, step: &'static str, message: F) -> Result<T, ContextError>
+    where
+        F: FnOnce() -> M,
+        M: Into<String>;
+}
+
+impl<T, E> ResultContext<T> for Result<T, E>
+where
+    E: Error + Send + Sync + 'static,
+{
+    fn context(self, step: &'static str, message: impl Into<String>) -> Result<T, ContextError> {
+        self.with_context(step, || message)
+    }
+
+    fn with_context<F, M>(self, step: &'static str, message: F) -> Result<T, ContextError>
+    where
+        F: FnOnce() -> M,
+        M: Into<String>,
+    {
+        self.map_err(|error| ContextError {
+            step,
+            message: message().into(),
+            source: Some(Box::new(error)),
+        })
+    }
+}
+// ANCHOR_END: context
+
+// ANCHOR: strict_checksum
+// Fails for content that is too long to checksum
+pub fn strict_checksum(data: EncryptedData) -> ProcessResult {
+    if data.content.len() > 16 {
+        return Err(ProcessError::ProcessingFailed);
+    }
+    crate::add_checksum(data)
+}
+// ANCHOR_END: strict_checksum
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MonadicResult;
+
+    fn encrypt_and_check(content: &str) -> Result<EncryptedData, ContextError> {
+        let data = EncryptedData {
+            content: content.to_string(),
+            key: 1,
+        };
+        crate::validate_data(data)
+            .context("validate", "checking the input")
+            .bind(|data| crate::apply_encryption(data).context("encrypt", "encrypting"))
+            .bind(|data| {
+                let content = data.content.clone();
+                strict_checksum(data)
+                    .with_context("checksum", || format!("adding a checksum to {content:?}"))
+            })
+    }
+
+    #[test]
+    fn names_the_step_and_keeps_the_source() {
+        let error = encrypt_and_check("a long secret").unwrap_err();
+        assert_eq!(error.step, "checksum");
+        assert_eq!(
+            error.to_string(),
+            "step `checksum`: adding a checksum to \"encrypted_a long secret\""
+        );
+        let source = error.source().unwrap();
+        assert!(matches!(
+            source.downcast_ref::<ProcessError>(),
+            Some(ProcessError::ProcessingFailed)
+        ));
+        assert!(encrypt_and_check("short").is_ok());
+    }
+
+    #[test]
+    fn frames_stack_and_the_report_walks_them() {
+        let error = encrypt_and_check("")
+            .context("store", "saving the record")
+            .unwrap_err();
+        assert_eq!(
+            error.report(),
+            "error: step `store`: saving the record\n\
+             caused by:\n  \
+             0: step `validate`: checking the input\n  \
+             1: invalid input"
+        );
+    }
+
+    #[test]
+    fn builds_the_message_only_on_error() {
+        let mut built = false;
+        let ok = Ok::<_, ProcessError>(1).with_context("noop", || {
+            built = true;
+            "unused"
+        });
+        assert_eq!(ok.unwrap(), 1);
+        assert!(!built);
+        let error = ContextError::new("check", "no data");
+        assert!(error.source().is_none());
+        assert_eq!(error.report(), "error: step `check`: no data");
+    }
+}
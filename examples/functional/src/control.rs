@@ -0,0 +1,191 @@
+//! Conditional and repeated steps that stay on the railway.
+
+// ANCHOR: control
+/// Runs `step` on values for which `predicate` holds; other values pass on
+/// unchanged.
+pub fn when<T, E>(
+    predicate: impl Fn(&T) -> bool,
+    step: impl Fn(T) -> Result<T, E>,
+) -> impl Fn(T) -> Result<T, E> {
+    move |value| {
+        if predicate(&value) {
+            step(value)
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+/// Runs `step` on values for which `predicate` does not hold.
+pub fn unless<T, E>(
+    predicate: impl Fn(&T) -> bool,
+    step: impl Fn(T) -> Result<T, E>,
+) -> impl Fn(T) -> Result<T, E> {
+    when(move |value| !predicate(value), step)
+}
+
+/// Runs the step at the index `selector` picks for the value, like the arms
+/// of a `match`, and `fallback` for an index past the last step, like its
+/// `_` arm. Pass `Ok` to let such values through unchanged.
+pub fn branch<T, E, S>(
+    selector: impl Fn(&T) -> usize,
+    steps: impl IntoIterator<Item = S>,
+    fallback: impl Fn(T) -> Result<T, E>,
+) -> impl Fn(T) -> Result<T, E>
+where
+    S: Fn(T) -> Result<T, E>,
+{
+    let steps: Vec<S> = steps.into_iter().collect();
+    move |value| match steps.get(selector(&value)) {
+        Some(step) => step(value),
+        None => fallback(value),
+    }
+}
+
+/// Runs `step` until `predicate` holds for its result or the step fails,
+/// at most `max` times. A value the predicate still rejects after that
+/// fails with `exhausted(value)`.
+pub fn repeat_until<T, E>(
+    predicate: impl Fn(&T) -> bool,
+    step: impl Fn(T) -> Result<T, E>,
+    max: usize,
+    exhausted: impl Fn(T) -> E,
+) -> impl Fn(T) -> Result<T, E> {
+    move |mut value| {
+        for _ in 0..max {
+            value = step(value)?;
+            if predicate(&value) {
+                return Ok(value);
+            }
+        }
+        Err(exhausted(value))
+    }
+}
+// ANCHOR_END: control
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::laws::{check, Gen};
+    use crate::pipeline::Pipeline;
+    use crate::*;
+    use std::cell::Cell;
+
+    fn odd_key(data: &EncryptedData) -> bool {
+        data.key % 2 != 0
+    }
+
+    #[test]
+    fn when_and_unless_pick_the_values_to_change() {
+        let checksum = when(odd_key, add_checksum);
+        assert_eq!(checksum(data("a", 1)).unwrap().content, "a_checksum");
+        assert_eq!(checksum(data("a", 2)).unwrap().content, "a");
+        let checksum = unless(odd_key, add_checksum);
+        assert_eq!(checksum(data("a", 1)).unwrap().content, "a");
+        assert_eq!(checksum(data("a", 2)).unwrap().content, "a_checksum");
+        let result = Ok(data("", 1)).bind(when(odd_key, validate_data));
+        assert!(matches!(result, Err(ProcessError::InvalidInput)));
+    }
+
+    #[test]
+    fn branch_runs_the_selected_step() {
+        let step = branch(
+            |data: &EncryptedData| data.key as usize,
+            [apply_encryption, add_checksum, finalize_process],
+            Ok,
+        );
+        let contents: Vec<_> = (0..4)
+            .map(|key| step(data("a", key)).unwrap().content)
+            .collect();
+        assert_eq!(contents, vec!["encrypted_a", "a_checksum", "final_a", "a"]);
+        let strict = branch(
+            |data: &EncryptedData| data.key as usize,
+            [apply_encryption],
+            |_| Err(ProcessError::InvalidInput),
+        );
+        assert_eq!(strict(data("a", 0)).unwrap().content, "encrypted_a");
+        assert!(matches!(
+            strict(data("a", 1)),
+            Err(ProcessError::InvalidInput)
+        ));
+        // Closures of different types need a box
+        type Step = Box<dyn Fn(i32) -> Result<i32, String>>;
+        let steps: Vec<Step> = vec![Box::new(validate_positive), Box::new(|x| Ok(x + 1))];
+        let step = branch(|x: &i32| x.rem_euclid(2) as usize, steps, Ok);
+        assert_eq!(step(-4), Err("Number must be positive".to_string()));
+        assert_eq!(step(-3), Ok(-2));
+    }
+
+    #[test]
+    fn repeat_until_runs_at_least_once_and_stops_on_error() {
+        let grow = repeat_until(
+            |data: &EncryptedData| data.content.len() > 30,
+            apply_encryption,
+            5,
+            |_| ProcessError::ProcessingFailed,
+        );
+        assert_eq!(
+            grow(data("a", 1)).unwrap().content,
+            "encrypted_encrypted_encrypted_a"
+        );
+        assert_eq!(grow(data(&"a".repeat(40), 1)).unwrap().content.len(), 50);
+        let runs = Cell::new(0);
+        let failing = repeat_until(
+            |_: &i32| false,
+            |x| {
+                runs.set(runs.get() + 1);
+                validate_positive(x - 1)
+            },
+            10,
+            |x| format!("still {x}"),
+        );
+        assert_eq!(failing(3), Err("Number must be positive".to_string()));
+        assert_eq!(runs.get(), 3);
+    }
+
+    #[test]
+    fn repeat_until_fails_after_max_runs() {
+        let runs = Cell::new(0);
+        let count = repeat_until(
+            |_: &i32| false,
+            |x| {
+                runs.set(runs.get() + 1);
+                Ok(x + 1)
+            },
+            4,
+            |x| format!("gave up at {x}"),
+        );
+        assert_eq!(count(0), Err("gave up at 4".to_string()));
+        assert_eq!(runs.get(), 4);
+        let never = repeat_until(|_: &i32| true, |x| Ok(x + 1), 0, |x| x);
+        assert_eq!(never(7), Err::<i32, i32>(7));
+    }
+
+    #[test]
+    fn combinators_fit_in_chains_and_pipelines() {
+        let pipeline = Pipeline::new()
+            .step("validate", validate_data)
+            .step("checksum", when(odd_key, add_checksum))
+            .step(
+                "encrypt",
+                repeat_until(
+                    |data| data.content.len() > 20,
+                    apply_encryption,
+                    3,
+                    |_| ProcessError::ProcessingFailed,
+                ),
+            );
+        let result = pipeline.run(data("a", 1)).unwrap();
+        assert_eq!(result.content, "encrypted_encrypted_a_checksum");
+        check("when", 500, |gen: &mut Gen| {
+            let x = gen.int(-1000, 1000);
+            let always = Ok(x).bind(when(|_| true, validate_positive));
+            let never = Ok(x).bind(when(|_| false, validate_positive));
+            if always != validate_positive(x) || never != Ok(x) {
+                return Err(format!("when changed the result for {x}"));
+            }
+            Ok(())
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+//! Steps with different error types in one chain.
+//!
+//! Each step keeps its own error type; `bind_into` converts it with `From`
+//! into the error type of the chain. `#[derive(PipelineError)]` writes the
+//! `From` impls for an enum with one variant per step error.
+
+use crate::MonadicResult;
+
+pub use traits_functional_derive::PipelineError;
+
+// ANCHOR: convert
+pub trait ResultInto<T, E> {
+    /// `bind` for a step with an error type of its own, converted into the
+    /// error type of the chain.
+    fn bind_into<U, E2, F>(self, f: F) -> Result<U, E>
+    where
+        F: FnOnce(T) -> Result<U, E2>,
+        E: From<E2>;
+
+    /// Converts the error, e.g. to start a chain in its final error type.
+    fn err_into<E2>(self) -> Result<T, E2>
+    where
+        E2: From<E>;
+}
+
+impl<T, E> ResultInto<T, E> for Result<T, E> {
+    fn bind_into<U, E2, F>(self, f: F) -> Result<U, E>
+    where
+        F: FnOnce(T) -> Result<U, E2>,
+        E: From<E2>,
+    {
+        self.bind(|value| f(value).fmap_err(E::from))
+    }
+
+    fn err_into<E2>(self) -> Result<T, E2>
+    where
+        E2: From<E>,
+    {
+        self.fmap_err(E2::from)
+    }
+}
+// ANCHOR_END: convert
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use std::io::{self, Read};
+    use std::num::ParseIntError;
+    use std::str::FromStr;
+
+    #[derive(Debug)]
+    enum Level {
+        Low,
+        High,
+    }
+
+    impl FromStr for Level {
+        type Err = String;
+
+        fn from_str(text: &str) -> Result<Self, String> {
+            match text {
+                "low" => Ok(Level::Low),
+                "high" => Ok(Level::High),
+                _ => Err(format!("unknown level {text:?}")),
+            }
+        }
+    }
+
+    #[derive(Debug, PipelineError)]
+    enum LoadError {
+        Io(io::Error),
+        /// Doc comments on variants are fine
+        Process(ProcessError),
+        Level(String),
+        Number(ParseIntError),
+        Nested(Result<Vec<u8>, Option<i32>>),
+    }
+
+    fn read(mut source: impl Read) -> io::Result<String> {
+        let mut text = String::new();
+        source.read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    /// Reads `content:key level`, one error type per step.
+    fn load(source: &[u8]) -> Result<(EncryptedData, Level), LoadError> {
+        Ok(source)
+            .bind_into(read)
+            .bind_into(|text| match text.split_once(' ') {
+                Some((data, level)) => Ok((data.to_string(), level.to_string())),
+                None => Err(ProcessError::InvalidInput),
+            })
+            .bind(|(data, level)| {
+                let data = parse_data(data).bind(validate_data)?;
+                Ok((data, level.parse()?))
+            })
+    }
+
+    #[test]
+    fn converts_the_error_of_each_step() {
+        let (data, level) = load(b"secret:7 high").unwrap();
+        assert_eq!((data.content.as_str(), data.key), ("secret", 7));
+        assert!(matches!(level, Level::High));
+        assert!(matches!(
+            load(b"secret:7"),
+            Err(LoadError::Process(ProcessError::InvalidInput))
+        ));
+        assert!(matches!(
+            load(b":7 low"),
+            Err(LoadError::Process(ProcessError::InvalidInput))
+        ));
+        match load(b"secret:7 medium") {
+            Err(LoadError::Level(message)) => assert_eq!(message, "unknown level \"medium\""),
+            other => panic!("expected a level error, got {other:?}"),
+        }
+        match load(&[0xff]) {
+            Err(LoadError::Io(error)) => assert_eq!(error.kind(), io::ErrorKind::InvalidData),
+            other => panic!("expected an I/O error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn err_into_starts_a_chain_in_the_final_error() {
+        let result: Result<i32, LoadError> = "12x".parse::<i32>().err_into();
+        match result {
+            Err(LoadError::Number(error)) => {
+                assert_eq!(error.to_string(), "invalid digit found in string")
+            }
+            other => panic!("expected a number error, got {other:?}"),
+        }
+        let result = "12"
+            .parse::<i32>()
+            .err_into::<LoadError>()
+            .bind_into(|n| Err::<i32, Result<Vec<u8>, Option<i32>>>(Err(Some(n))));
+        assert!(matches!(result, Err(LoadError::Nested(Err(Some(12))))));
+        let kept: Result<u8, LoadError> = Ok(3).bind_into(|n| Ok::<_, String>(n + 1));
+        assert_eq!(kept.unwrap(), 4);
+    }
+}
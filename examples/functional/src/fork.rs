@@ -0,0 +1,476 @@
+//! Independent steps run in parallel, then joined into one result.
+
+use crate::pipeline::StepError;
+
+// ANCHOR: fork
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+
+/// What a join returns when some branches fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinMode {
+    /// Every branch runs; the error of the first failed branch, in the
+    /// order the branches were added, is returned.
+    FirstError,
+    /// Every branch runs; the errors of all failed branches are returned.
+    AllErrors,
+    /// The first failure cancels the branches that have not started yet.
+    /// Branches already running are not interrupted; the errors of all
+    /// failed branches are returned with the names of the cancelled ones.
+    Cancel,
+}
+
+type BranchFn<T, U, E> = Arc<dyn Fn(&T) -> Result<U, E> + Send + Sync>;
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Named branches that all take the same input and return the same type.
+/// They run on a pool of up to `workers` threads, which starts with the
+/// first join and is reused by every later one until the fork is dropped.
+pub struct Fork<T, U, E> {
+    branches: Vec<(&'static str, BranchFn<T, U, E>)>,
+    workers: usize,
+    mode: JoinMode,
+    pool: OnceLock<Pool>,
+}
+
+/// The error of a failed join.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForkError<E> {
+    /// The failed branches, in the order they were added.
+    /// `JoinMode::FirstError` returns only the first of them.
+    pub errors: Vec<StepError<E>>,
+    /// The branches that never ran, with `JoinMode::Cancel`.
+    pub cancelled: Vec<&'static str>,
+}
+
+impl<T, U, E> Fork<T, U, E> {
+    /// A fork with one worker per available CPU that returns the first error.
+    pub fn new() -> Self {
+        Fork {
+            branches: Vec::new(),
+            workers: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            mode: JoinMode::FirstError,
+            pool: OnceLock::new(),
+        }
+    }
+
+    pub fn branch<F>(mut self, name: &'static str, f: F) -> Self
+    where
+        F: Fn(&T) -> Result<U, E> + Send + Sync + 'static,
+    {
+        self.branches.push((name, Arc::new(f)));
+        // The pool is sized for the branches, so the next join starts anew.
+        self.pool = OnceLock::new();
+        self
+    }
+
+    /// How many branches may run at the same time, at least one.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self.pool = OnceLock::new();
+        self
+    }
+
+    pub fn mode(mut self, mode: JoinMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Runs every branch on `input` and, if none fails, passes the input
+    /// and the branch results, in the order the branches were added, to
+    /// `combine`.
+    ///
+    /// The pool's threads outlive the join, so the input is moved into it
+    /// and handed back to `combine`. A panicking branch panics the join.
+    pub fn join<R, C>(&self, input: T, combine: C) -> Result<R, ForkError<E>>
+    where
+        T: Send + Sync + 'static,
+        U: Send + 'static,
+        E: Send + 'static,
+        C: FnOnce(T, Vec<U>) -> R,
+    {
+        let run = Arc::new(Run::new(self, input));
+        let jobs = self.workers.min(self.branches.len());
+        let (done, finished) = mpsc::channel();
+        if jobs > 0 {
+            let pool = self.pool.get_or_init(|| Pool::new(jobs));
+            for _ in 0..jobs {
+                let (run, done) = (run.clone(), done.clone());
+                pool.spawn(Box::new(move || {
+                    run.work();
+                    // Let go of the input before the join takes it back.
+                    drop(run);
+                    let _ = done.send(());
+                }));
+            }
+        }
+        drop(done);
+        for _ in 0..jobs {
+            // A job that panicked drops its sender without sending.
+            finished.recv().expect("a branch of the fork panicked");
+        }
+        let Ok(run) = Arc::try_unwrap(run) else {
+            unreachable!("every job has finished with the input");
+        };
+        run.finish(combine)
+    }
+}
+
+/// The state the jobs of one join share.
+struct Run<T, U, E> {
+    input: T,
+    branches: Vec<(&'static str, BranchFn<T, U, E>)>,
+    mode: JoinMode,
+    next: AtomicUsize,
+    cancelled: AtomicBool,
+    slots: Vec<Mutex<Option<Result<U, E>>>>,
+}
+
+impl<T, U, E> Run<T, U, E> {
+    fn new(fork: &Fork<T, U, E>, input: T) -> Self {
+        Run {
+            input,
+            branches: fork.branches.clone(),
+            mode: fork.mode,
+            next: AtomicUsize::new(0),
+            cancelled: AtomicBool::new(false),
+            slots: fork.branches.iter().map(|_| Mutex::new(None)).collect(),
+        }
+    }
+
+    /// Runs branches until none is left or the join is cancelled.
+    fn work(&self) {
+        while let Some(index) = self.claim() {
+            self.execute(index);
+        }
+    }
+
+    /// The next branch to run. The check for a cancelled join comes after
+    /// the branch is taken, so a failure just before it still counts.
+    fn claim(&self) -> Option<usize> {
+        let index = self.next.fetch_add(1, Ordering::SeqCst);
+        let cancelled = self.cancelled.load(Ordering::SeqCst);
+        (index < self.branches.len() && !cancelled).then_some(index)
+    }
+
+    fn execute(&self, index: usize) {
+        let result = (self.branches[index].1)(&self.input);
+        if result.is_err() && self.mode == JoinMode::Cancel {
+            self.cancelled.store(true, Ordering::SeqCst);
+        }
+        *self.slots[index].lock().unwrap() = Some(result);
+    }
+
+    /// Combines the results, or collects the errors and the branches that
+    /// never ran.
+    fn finish<R>(self, combine: impl FnOnce(T, Vec<U>) -> R) -> Result<R, ForkError<E>> {
+        let mut values = Vec::new();
+        let mut error = ForkError {
+            errors: Vec::new(),
+            cancelled: Vec::new(),
+        };
+        for ((step, _), slot) in self.branches.into_iter().zip(self.slots) {
+            match slot.into_inner().unwrap() {
+                Some(Ok(value)) => values.push(value),
+                Some(Err(failure)) => {
+                    if self.mode != JoinMode::FirstError || error.errors.is_empty() {
+                        error.errors.push(StepError {
+                            step,
+                            error: failure,
+                        });
+                    }
+                }
+                None => error.cancelled.push(step),
+            }
+        }
+        match error.errors.is_empty() {
+            true => Ok(combine(self.input, values)),
+            false => Err(error),
+        }
+    }
+}
+
+/// Threads that take jobs from a queue until the pool is dropped.
+struct Pool {
+    jobs: Option<mpsc::Sender<Job>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl Pool {
+    fn new(size: usize) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        let threads = (0..size)
+            .map(|_| {
+                let queue = queue.clone();
+                thread::spawn(move || loop {
+                    let job = queue.lock().unwrap().recv();
+                    let Ok(job) = job else { break };
+                    // The join reports the panic; the thread takes the next job.
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                })
+            })
+            .collect();
+        Pool {
+            jobs: Some(jobs),
+            threads,
+        }
+    }
+
+    fn spawn(&self, job: Job) {
+        let jobs = self
+            .jobs
+            .as_ref()
+            .expect("the queue is open until the drop");
+        jobs.send(job)
+            .expect("the threads run until the pool is dropped");
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        // Closing the queue ends every thread after its current job.
+        self.jobs = None;
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for ForkError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{error}")?;
+        }
+        if !self.cancelled.is_empty() {
+            let names: Vec<String> = self
+                .cancelled
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect();
+            write!(f, "; cancelled {}", names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for ForkError<E> {}
+// ANCHOR_END: fork
+
+impl<T, U, E> Default for Fork<T, U, E> {
+    fn default() -> Self {
+        Fork::new()
+    }
+}
+
+impl<T, U, E> fmt::Debug for Fork<T, U, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.branches.iter().map(|(name, _)| *name).collect();
+        f.debug_struct("Fork")
+            .field("branches", &names)
+            .field("workers", &self.workers)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use std::sync::{Arc, Barrier};
+    use std::time::Duration;
+
+    fn fail(message: &'static str) -> impl Fn(&i32) -> Result<i32, String> {
+        move |_| Err(message.to_string())
+    }
+
+    /// A branch adding `n` that counts how often it ran.
+    fn counted(runs: &Arc<AtomicUsize>, n: i32) -> impl Fn(&i32) -> Result<i32, String> {
+        let runs = runs.clone();
+        move |x| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Ok(x + n)
+        }
+    }
+
+    #[test]
+    fn combines_the_results_in_branch_order() {
+        let fork = Fork::new()
+            .branch("slow", |x: &i32| {
+                thread::sleep(Duration::from_millis(20));
+                Ok(x * 10)
+            })
+            .branch("fast", |x| Ok(x + 1))
+            .workers(2);
+        let joined = fork.join(4, |input, results: Vec<i32>| (input, results));
+        assert_eq!(joined, Ok::<_, ForkError<String>>((4, vec![40, 5])));
+    }
+
+    #[test]
+    fn branches_run_at_the_same_time() {
+        // Each branch waits for the other, so running them one after the
+        // other would never finish
+        let barrier = Arc::new(Barrier::new(2));
+        let waiting = barrier.clone();
+        let fork = Fork::new()
+            .branch("left", move |x: &i32| {
+                barrier.wait();
+                Ok(*x)
+            })
+            .branch("right", move |x| {
+                waiting.wait();
+                validate_positive(*x)
+            })
+            .workers(2);
+        assert_eq!(
+            fork.join(3, |_, results| results.iter().sum::<i32>()),
+            Ok(6)
+        );
+    }
+
+    #[test]
+    fn first_error_runs_every_branch() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let fork = Fork::new()
+            .branch("ok", counted(&runs, 1))
+            .branch("a", fail("a"))
+            .branch("b", fail("b"))
+            .branch("also ok", counted(&runs, 2));
+        let error = fork.join(1, |_, _| ()).unwrap_err();
+        assert_eq!(
+            error.errors,
+            vec![StepError {
+                step: "a",
+                error: "a".to_string()
+            }]
+        );
+        assert!(error.cancelled.is_empty());
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(error.to_string(), "step `a` failed: \"a\"");
+    }
+
+    #[test]
+    fn all_errors_keeps_every_failure() {
+        let fork = Fork::new()
+            .branch("a", fail("a"))
+            .branch("ok", |x: &i32| Ok(*x))
+            .branch("b", fail("b"))
+            .mode(JoinMode::AllErrors);
+        let error = fork.join(1, |_, _| ()).unwrap_err();
+        let failed: Vec<_> = error.errors.iter().map(|error| error.step).collect();
+        assert_eq!(failed, vec!["a", "b"]);
+        assert_eq!(
+            error.to_string(),
+            "step `a` failed: \"a\"; step `b` failed: \"b\""
+        );
+    }
+
+    #[test]
+    fn cancel_skips_the_branches_not_started() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let fork = Fork::new()
+            .branch("ok", counted(&runs, 1))
+            .branch("fail", fail("stop"))
+            .branch("c", counted(&runs, 2))
+            .branch("d", counted(&runs, 3))
+            .mode(JoinMode::Cancel)
+            .workers(1);
+        let error = fork.join(1, |_, _| ()).unwrap_err();
+        assert_eq!(error.errors.len(), 1);
+        assert_eq!(error.errors[0].step, "fail");
+        assert_eq!(error.cancelled, vec!["c", "d"]);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            error.to_string(),
+            "step `fail` failed: \"stop\"; cancelled `c`, `d`"
+        );
+    }
+
+    #[test]
+    fn cancel_keeps_the_errors_of_branches_already_running() {
+        // Both failing branches are running before either fails
+        let barrier = Arc::new(Barrier::new(2));
+        let waiting = barrier.clone();
+        let fork = Fork::new()
+            .branch("a", move |_: &i32| {
+                barrier.wait();
+                Err("a".to_string())
+            })
+            .branch("b", move |_| {
+                waiting.wait();
+                Err("b".to_string())
+            })
+            .branch("c", |x| Ok(*x))
+            .mode(JoinMode::Cancel)
+            .workers(2);
+        let error = fork.join(1, |_, _| ()).unwrap_err();
+        let failed: Vec<_> = error.errors.iter().map(|error| error.step).collect();
+        assert_eq!(failed, vec!["a", "b"]);
+        assert_eq!(error.cancelled, vec!["c"]);
+    }
+
+    #[test]
+    fn a_branch_taken_after_a_failure_does_not_run() {
+        // A worker that found the join running can take its next branch
+        // only after another worker has failed; that branch must not run
+        let runs = Arc::new(AtomicUsize::new(0));
+        let fork = Fork::new()
+            .branch("fail", fail("stop"))
+            .branch("late", counted(&runs, 1))
+            .mode(JoinMode::Cancel);
+        let run = Run::new(&fork, 1);
+        assert_eq!(run.claim(), Some(0));
+        run.execute(0);
+        assert_eq!(run.claim(), None);
+        run.work();
+        let error = run.finish(|_, _| ()).unwrap_err();
+        assert_eq!(error.cancelled, vec!["late"]);
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn joins_reuse_the_pool() {
+        let threads = Arc::new(Mutex::new(Vec::new()));
+        let seen = threads.clone();
+        let fork = Fork::new()
+            .branch("record", move |x: &i32| {
+                seen.lock().unwrap().push(thread::current().id());
+                Ok::<_, String>(*x)
+            })
+            .workers(1);
+        for input in 0..3 {
+            assert_eq!(fork.join(input, |_, results| results), Ok(vec![input]));
+        }
+        let threads = threads.lock().unwrap();
+        assert_eq!(threads.len(), 3);
+        assert!(threads.iter().all(|id| *id == threads[0]));
+        assert_ne!(threads[0], thread::current().id());
+    }
+
+    #[test]
+    fn a_panicking_branch_panics_the_join_and_keeps_the_pool() {
+        let fork = Fork::new()
+            .branch("panic", |x: &i32| match *x {
+                0 => panic!("no input"),
+                x => Ok::<_, String>(x),
+            })
+            .workers(1);
+        let joined = panic::catch_unwind(AssertUnwindSafe(|| fork.join(0, |_, _| ())));
+        assert!(joined.is_err());
+        assert_eq!(fork.join(2, |_, results| results), Ok(vec![2]));
+    }
+
+    #[test]
+    fn an_empty_fork_joins_at_once() {
+        let fork: Fork<i32, i32, String> = Fork::default().workers(0);
+        assert_eq!(fork.join(7, |x, results| (x, results.len())), Ok((7, 0)));
+    }
+}
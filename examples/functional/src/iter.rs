@@ -0,0 +1,105 @@
+//! The railway for iterators of results.
+
+// ANCHOR: monadic_iterator
+pub trait MonadicIterator<T, E>: Iterator<Item = Result<T, E>> + Sized {
+    /// Runs `f` on every `Ok` element; `Err` elements pass through untouched.
+    fn bind_each<U, F>(self, mut f: F) -> impl Iterator<Item = Result<U, E>>
+    where
+        F: FnMut(T) -> Result<U, E>,
+    {
+        self.map(move |item| match item {
+            Ok(value) => f(value),
+            Err(error) => Err(error),
+        })
+    }
+
+    fn fmap_each<U, F>(self, mut f: F) -> impl Iterator<Item = Result<U, E>>
+    where
+        F: FnMut(T) -> U,
+    {
+        self.bind_each(move |value| Ok(f(value)))
+    }
+
+    /// All the values, or the first error. No element after the first
+    /// error is taken from the iterator, so no step runs on it.
+    fn stop_on_error(self) -> Result<Vec<T>, E> {
+        self.collect()
+    }
+
+    /// All the values, or every error.
+    fn collect_errors(self) -> Result<Vec<T>, Vec<E>> {
+        let (mut values, mut errors) = (Vec::new(), Vec::new());
+        for item in self {
+            match item {
+                Ok(value) => values.push(value),
+                Err(error) => errors.push(error),
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(values),
+            false => Err(errors),
+        }
+    }
+}
+
+impl<T, E, I> MonadicIterator<T, E> for I where I: Iterator<Item = Result<T, E>> {}
+// ANCHOR_END: monadic_iterator
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{double, validate_positive};
+
+    fn inputs(numbers: &[i32]) -> impl Iterator<Item = Result<i32, String>> + '_ {
+        numbers.iter().copied().map(Ok)
+    }
+
+    #[test]
+    fn applies_steps_to_every_element() {
+        let doubled = inputs(&[1, 2, 3])
+            .bind_each(validate_positive)
+            .bind_each(double)
+            .fmap_each(|x| x + 1)
+            .stop_on_error();
+        assert_eq!(doubled, Ok(vec![3, 5, 7]));
+    }
+
+    #[test]
+    fn stop_on_error_runs_no_step_after_the_first_error() {
+        let mut doubled = Vec::new();
+        let result = inputs(&[1, -2, 3, -4])
+            .bind_each(validate_positive)
+            .bind_each(|x| {
+                doubled.push(x);
+                double(x)
+            })
+            .stop_on_error();
+        assert_eq!(result, Err("Number must be positive".to_string()));
+        assert_eq!(doubled, vec![1]);
+    }
+
+    #[test]
+    fn collect_errors_keeps_every_error() {
+        let result = inputs(&[1, -2, 3, -4])
+            .bind_each(validate_positive)
+            .bind_each(double)
+            .collect_errors();
+        assert_eq!(result.unwrap_err().len(), 2);
+        assert_eq!(inputs(&[5]).collect_errors(), Ok(vec![5]));
+    }
+
+    #[test]
+    fn errors_pass_through_later_steps() {
+        let items = vec![Err("bad input".to_string()), Ok(2)];
+        let mut calls = 0;
+        let results: Vec<_> = items
+            .into_iter()
+            .bind_each(|x| {
+                calls += 1;
+                double(x)
+            })
+            .collect();
+        assert_eq!(results, vec![Err("bad input".to_string()), Ok(4)]);
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,182 @@
+//! Steps composed into one function value before any data is given.
+
+use crate::MonadicResult;
+
+// ANCHOR: kleisli
+use std::fmt;
+use std::ops::Shr;
+use std::sync::Arc;
+
+/// A step from `A` to `Result<B, E>` as a value: it can be composed with the
+/// next step, stored in a struct, cloned and called later.
+pub struct Kleisli<A, B, E> {
+    step: Arc<dyn Fn(A) -> Result<B, E> + Send + Sync>,
+}
+
+impl<A, B, E> Kleisli<A, B, E> {
+    pub fn new<F>(step: F) -> Self
+    where
+        F: Fn(A) -> Result<B, E> + Send + Sync + 'static,
+    {
+        Kleisli {
+            step: Arc::new(step),
+        }
+    }
+
+    pub fn call(&self, input: A) -> Result<B, E> {
+        (self.step)(input)
+    }
+
+    /// The step that runs this one, then `next` on its success value.
+    pub fn then<C>(self, next: impl Into<Kleisli<B, C, E>>) -> Kleisli<A, C, E>
+    where
+        A: 'static,
+        B: 'static,
+        C: 'static,
+        E: 'static,
+    {
+        let next = next.into();
+        Kleisli::new(move |input| self.call(input).bind(|value| next.call(value)))
+    }
+
+    /// This step as a closure, e.g. for `bind` or `Pipeline::step`.
+    pub fn into_fn(self) -> impl Fn(A) -> Result<B, E> + Send + Sync {
+        move |input| self.call(input)
+    }
+}
+
+impl<A, E> Kleisli<A, A, E> {
+    /// The step that passes its input on unchanged: `Ok`.
+    pub fn identity() -> Self
+    where
+        A: 'static,
+        E: 'static,
+    {
+        Kleisli::new(Ok)
+    }
+}
+
+impl<A, B, E, F> From<F> for Kleisli<A, B, E>
+where
+    F: Fn(A) -> Result<B, E> + Send + Sync + 'static,
+{
+    fn from(step: F) -> Self {
+        Kleisli::new(step)
+    }
+}
+
+/// `f >> g` is `f.then(g)`, Haskell's `f >=> g`.
+impl<A, B, C, E> Shr<Kleisli<B, C, E>> for Kleisli<A, B, E>
+where
+    A: 'static,
+    B: 'static,
+    C: 'static,
+    E: 'static,
+{
+    type Output = Kleisli<A, C, E>;
+
+    fn shr(self, next: Kleisli<B, C, E>) -> Self::Output {
+        self.then(next)
+    }
+}
+
+/// Another handle to the same composed closure. `#[derive(Clone)]` would
+/// require `A`, `B` and `E` to be `Clone`, though no value of them is copied.
+impl<A, B, E> Clone for Kleisli<A, B, E> {
+    fn clone(&self) -> Self {
+        Kleisli {
+            step: self.step.clone(),
+        }
+    }
+}
+
+/// The arrow's signature, e.g. `Kleisli(i32 -> Result<u8, bool>)`: the
+/// closure inside has nothing to print.
+impl<A, B, E> fmt::Debug for Kleisli<A, B, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Kleisli({} -> Result<{}, {}>)",
+            std::any::type_name::<A>(),
+            std::any::type_name::<B>(),
+            std::any::type_name::<E>()
+        )
+    }
+}
+// ANCHOR_END: kleisli
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::laws::{check, Gen};
+    use crate::pipeline::Pipeline;
+    use crate::*;
+
+    type Step = Kleisli<EncryptedData, EncryptedData, ProcessError>;
+
+    fn encrypt() -> Step {
+        Kleisli::new(validate_data)
+            .then(apply_encryption)
+            .then(add_checksum)
+            .then(finalize_process)
+    }
+
+    #[test]
+    fn composes_without_a_starting_ok() {
+        let result = encrypt().call(data("secret", 123)).unwrap();
+        assert_eq!(result.content, "final_encrypted_secret_checksum");
+        assert_eq!(result.key, 124);
+        assert!(matches!(
+            encrypt().call(data("", 123)),
+            Err(ProcessError::InvalidInput)
+        ));
+    }
+
+    #[test]
+    fn is_a_value_to_store_and_pass_around() {
+        struct Processor {
+            encrypt: Step,
+            measure: Kleisli<EncryptedData, usize, ProcessError>,
+        }
+        let processor = Processor {
+            encrypt: encrypt(),
+            measure: encrypt().then(|data: EncryptedData| Ok(data.content.len())),
+        };
+        let copy = processor.encrypt.clone();
+        assert_eq!(
+            copy.call(data("a", 123)).unwrap().content,
+            "final_encrypted_a_checksum"
+        );
+        assert_eq!(processor.measure.call(data("a", 123)).unwrap(), 26);
+
+        let from_chain = Ok(data("a", 123)).bind(processor.encrypt.into_fn());
+        assert_eq!(from_chain.unwrap().key, 124);
+        let pipeline = Pipeline::new().step("encrypt", encrypt().into_fn());
+        assert_eq!(pipeline.run(data("a", 123)).unwrap().key, 124);
+        assert_eq!(
+            format!("{:?}", Kleisli::<i32, u8, String>::new(|_| Ok(0))),
+            "Kleisli(i32 -> Result<u8, alloc::string::String>)"
+        );
+    }
+
+    #[test]
+    fn shr_composes_like_then_and_identity_is_neutral() {
+        check("kleisli", 500, |gen: &mut Gen| {
+            let x = gen.int(-1000, 1000);
+            let positive = Kleisli::new(validate_positive);
+            let doubled = Kleisli::new(double);
+            let chain = Ok(x).bind(validate_positive).bind(double).bind(double);
+            let grouped_left = (positive.clone() >> doubled.clone()) >> doubled.clone();
+            let grouped_right = positive.clone() >> (doubled.clone() >> doubled.clone());
+            let with_identity = Kleisli::identity() >> positive >> Kleisli::identity();
+            if grouped_left.call(x) != chain || grouped_right.call(x) != chain {
+                return Err(format!("grouping changed the result for {x}"));
+            }
+            if with_identity.call(x) != validate_positive(x) {
+                return Err(format!("identity changed the result for {x}"));
+            }
+            Ok(())
+        })
+        .unwrap();
+    }
+}
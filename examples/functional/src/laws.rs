@@ -0,0 +1,410 @@
+//! The laws that `bind` follows, and a small property harness to check them.
+//!
+//! These hold for every step, whatever it does:
+//!
+//! - left identity: `Ok(x).bind(f)` is `f(x)`
+//! - right identity: `m.bind(Ok)` is `m`
+//! - associativity: `m.bind(f).bind(g)` is `m.bind(|x| f(x).bind(g))`, so a
+//!   chain may be split into sub-chains and regrouped freely
+//! - Kleisli composition: composing steps with [`Kleisli::then`] is
+//!   associative, with [`Kleisli::identity`] as its identity on both sides
+//! - short-circuit: `Err(e).bind(f)` is `Err(e)` and never calls `f`
+//!
+//! Commutativity is not one of them: `f` then `g` is in general not `g` then
+//! `f`, so the steps of a chain cannot be put in any order. `add_checksum`
+//! happens to commute with `apply_encryption`, which adds a prefix where it
+//! adds a suffix, but not with `validate_data`: on empty content, validating
+//! first fails, while a checksum first makes the content valid. [`commutes`]
+//! checks the property for steps that are expected to commute.
+
+use crate::kleisli::Kleisli;
+use crate::MonadicResult;
+
+// ANCHOR: laws
+use std::cell::Cell;
+use std::fmt;
+
+/// `Ok` if the law held, or a description of the counterexample.
+pub type LawResult = Result<(), String>;
+
+// Results are compared through their `Debug` output, so that errors such as
+// `ProcessError` need not implement `PartialEq`
+fn same<A: fmt::Debug>(law: &str, left: A, right: A) -> LawResult {
+    let (left, right) = (format!("{left:?}"), format!("{right:?}"));
+    if left == right {
+        Ok(())
+    } else {
+        Err(format!("{law}: {left} != {right}"))
+    }
+}
+
+pub fn left_identity<T, U, E>(x: T, f: impl Fn(T) -> Result<U, E>) -> LawResult
+where
+    T: Clone,
+    U: fmt::Debug,
+    E: fmt::Debug,
+{
+    same("left identity", Ok(x.clone()).bind(&f), f(x))
+}
+
+/// Checks `m.bind(Ok)` against `m`, with `m` the result of `f(x)`.
+pub fn right_identity<T, U, E>(x: T, f: impl Fn(T) -> Result<U, E>) -> LawResult
+where
+    T: Clone,
+    U: fmt::Debug,
+    E: fmt::Debug,
+{
+    same("right identity", f(x.clone()).bind(Ok), f(x))
+}
+
+/// Checks associativity, with `m` the result of `f(x)`.
+pub fn associativity<T, U, V, W, E>(
+    x: T,
+    f: impl Fn(T) -> Result<U, E>,
+    g: impl Fn(U) -> Result<V, E>,
+    h: impl Fn(V) -> Result<W, E>,
+) -> LawResult
+where
+    T: Clone,
+    W: fmt::Debug,
+    E: fmt::Debug,
+{
+    let left = f(x.clone()).bind(&g).bind(&h);
+    let right = f(x).bind(|u| g(u).bind(&h));
+    same("associativity", left, right)
+}
+
+/// Checks that `then` is associative on `f`, `g` and `h`, and that
+/// `Kleisli::identity()` changes nothing on either side of `f`.
+pub fn kleisli_composition<T, U, V, W, E>(
+    x: T,
+    f: Kleisli<T, U, E>,
+    g: Kleisli<U, V, E>,
+    h: Kleisli<V, W, E>,
+) -> LawResult
+where
+    T: Clone + 'static,
+    U: fmt::Debug + 'static,
+    V: 'static,
+    W: fmt::Debug + 'static,
+    E: fmt::Debug + 'static,
+{
+    let left = f.clone().then(g.clone()).then(h.clone());
+    let right = f.clone().then(g.then(h));
+    same(
+        "Kleisli associativity",
+        left.call(x.clone()),
+        right.call(x.clone()),
+    )?;
+    let identity_first = Kleisli::identity().then(f.clone());
+    let identity_last = f.clone().then(Kleisli::identity());
+    same(
+        "Kleisli left identity",
+        identity_first.call(x.clone()),
+        f.call(x.clone()),
+    )?;
+    same(
+        "Kleisli right identity",
+        identity_last.call(x.clone()),
+        f.call(x),
+    )
+}
+
+pub fn short_circuit<T, U, E>(error: E, f: impl Fn(T) -> Result<U, E>) -> LawResult
+where
+    U: fmt::Debug,
+    E: fmt::Debug,
+{
+    let expected = format!("Err({error:?})");
+    let called = Cell::new(false);
+    let result = Err(error).bind(|x| {
+        called.set(true);
+        f(x)
+    });
+    if called.get() {
+        return Err("short-circuit: the step ran after an error".to_string());
+    }
+    same("short-circuit", format!("{result:?}"), expected)
+}
+
+/// Not a law: holds only for steps that do not depend on each other.
+pub fn commutes<T, E>(
+    x: T,
+    f: impl Fn(T) -> Result<T, E>,
+    g: impl Fn(T) -> Result<T, E>,
+) -> LawResult
+where
+    T: Clone + fmt::Debug,
+    E: fmt::Debug,
+{
+    same(
+        "commutativity",
+        Ok(x.clone()).bind(&f).bind(&g),
+        Ok(x).bind(&g).bind(&f),
+    )
+}
+// ANCHOR_END: laws
+
+// ANCHOR: harness
+/// Generates test values from a seed, the same values for the same seed.
+pub struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    pub fn new(seed: u64) -> Self {
+        // xorshift never leaves zero
+        Gen { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number in `low..=high`. Panics if `low > high`.
+    pub fn int(&mut self, low: i32, high: i32) -> i32 {
+        assert!(
+            low <= high,
+            "Gen::int needs low <= high, got {low}..={high}"
+        );
+        let span = (i64::from(high) - i64::from(low) + 1) as u64;
+        (i64::from(low) + (self.next_u64() % span) as i64) as i32
+    }
+
+    /// Up to `max_len` lowercase letters, possibly none.
+    pub fn string(&mut self, max_len: usize) -> String {
+        let len = self.next_u64() as usize % (max_len + 1);
+        (0..len)
+            .map(|_| char::from(b'a' + (self.next_u64() % 26) as u8))
+            .collect()
+    }
+
+    /// One of `choices`. Panics if there are none.
+    pub fn pick<'a, A>(&mut self, choices: &'a [A]) -> &'a A {
+        assert!(!choices.is_empty(), "Gen::pick needs at least one choice");
+        &choices[self.next_u64() as usize % choices.len()]
+    }
+}
+
+/// Runs `property` on `cases` generated cases and returns the first
+/// counterexample, with the seed that reproduces it.
+pub fn check<P>(name: &str, cases: u64, property: P) -> LawResult
+where
+    P: Fn(&mut Gen) -> LawResult,
+{
+    for case in 0..cases {
+        let seed = (case + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        if let Err(counterexample) = property(&mut Gen::new(seed)) {
+            return Err(format!(
+                "{name} failed on case {case} (seed {seed:#x}): {counterexample}"
+            ));
+        }
+    }
+    Ok(())
+}
+// ANCHOR_END: harness
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::strict_checksum;
+    use crate::option::MonadicOption;
+    use crate::pipeline::Pipeline;
+    use crate::saga::Saga;
+    use crate::*;
+
+    const CASES: u64 = 500;
+
+    type Step = fn(EncryptedData) -> ProcessResult;
+
+    const STEPS: [Step; 5] = [
+        validate_data,
+        apply_encryption,
+        add_checksum,
+        finalize_process,
+        strict_checksum,
+    ];
+
+    fn input(gen: &mut Gen) -> (String, i32) {
+        (gen.string(12), gen.int(-1000, 1000))
+    }
+
+    /// A step from the clonable input, so that the laws can run it twice.
+    fn start(step: Step) -> impl Fn((String, i32)) -> ProcessResult + Copy {
+        move |(content, key): (String, i32)| step(data(&content, key))
+    }
+
+    fn odd(x: i32) -> Result<i32, String> {
+        match x % 2 {
+            0 => Err(format!("{x} is even")),
+            _ => Ok(x),
+        }
+    }
+
+    const NUMBER_STEPS: [fn(i32) -> Result<i32, String>; 3] = [validate_positive, double, odd];
+
+    #[test]
+    fn bind_follows_the_monad_laws() {
+        check("left identity", CASES, |gen| {
+            left_identity(input(gen), start(*gen.pick(&STEPS)))
+        })
+        .unwrap();
+        check("right identity", CASES, |gen| {
+            right_identity(input(gen), start(*gen.pick(&STEPS)))
+        })
+        .unwrap();
+        check("associativity", CASES, |gen| {
+            let (f, g, h) = (*gen.pick(&STEPS), *gen.pick(&STEPS), *gen.pick(&STEPS));
+            associativity(input(gen), start(f), g, h)
+        })
+        .unwrap();
+        check("numbers", CASES, |gen| {
+            let x = gen.int(-1_000_000, 1_000_000);
+            let (f, g, h) = (
+                *gen.pick(&NUMBER_STEPS),
+                *gen.pick(&NUMBER_STEPS),
+                *gen.pick(&NUMBER_STEPS),
+            );
+            left_identity(x, f)?;
+            right_identity(x, f)?;
+            associativity(x, f, g, h)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn composed_steps_follow_the_kleisli_laws() {
+        check("kleisli", CASES, |gen| {
+            let f = Kleisli::new(start(*gen.pick(&STEPS)));
+            let (g, h) = (
+                Kleisli::new(*gen.pick(&STEPS)),
+                Kleisli::new(*gen.pick(&STEPS)),
+            );
+            kleisli_composition(input(gen), f, g, h)
+        })
+        .unwrap();
+        check("kleisli numbers", CASES, |gen| {
+            let x = gen.int(-1_000_000, 1_000_000);
+            let [f, g, h] = [(); 3].map(|_| Kleisli::new(*gen.pick(&NUMBER_STEPS)));
+            kleisli_composition(x, f, g, h)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn an_error_skips_every_step() {
+        check("short-circuit", CASES, |gen| {
+            let error = gen.pick(&["InvalidInput", "ProcessingFailed"]).to_string();
+            short_circuit(error, *gen.pick(&NUMBER_STEPS))?;
+            short_circuit(ProcessError::ValidationFailed, *gen.pick(&STEPS))
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn only_independent_steps_commute() {
+        let lift = |step: Step| {
+            move |(content, key): (String, i32)| {
+                step(data(&content, key)).fmap(|d| (d.content, d.key))
+            }
+        };
+        // A prefix and a suffix
+        check("commutativity", CASES, |gen| {
+            commutes(input(gen), lift(apply_encryption), lift(add_checksum))
+        })
+        .unwrap();
+        let failure = check("commutativity", CASES, |gen| {
+            commutes(input(gen), lift(validate_data), lift(add_checksum))
+        })
+        .unwrap_err();
+        assert!(
+            failure.contains("Err(InvalidInput) != Ok((\"_checksum\""),
+            "{failure}"
+        );
+        // Doubling does not change the sign
+        check("commutativity", CASES, |gen| {
+            commutes(gen.int(-1_000_000, 1_000_000), validate_positive, double)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn combinators_agree_with_bind() {
+        check("combinators", CASES, |gen| {
+            let (f, g, h) = (*gen.pick(&STEPS), *gen.pick(&STEPS), *gen.pick(&STEPS));
+            let x = input(gen);
+            let chain = Ok(data(&x.0, x.1)).bind(f).bind(g).bind(h);
+
+            let pipeline = Pipeline::new().step("f", f).step("g", g).step("h", h);
+            same(
+                "pipeline",
+                pipeline.run(data(&x.0, x.1)).map_err(|e| e.error),
+                chain,
+            )?;
+
+            let saga = Saga::new().step("f", f).step("g", g).step("h", h);
+            let chain = Ok(data(&x.0, x.1)).bind(f).bind(g).bind(h);
+            same(
+                "saga",
+                saga.run(data(&x.0, x.1)).map_err(|e| e.failed.error),
+                chain,
+            )?;
+
+            let chain = Ok(data(&x.0, x.1)).bind(f).bind(g).bind(h);
+            let railway = railway! {
+                let a <- f(data(&x.0, x.1));
+                let b <- g(a);
+                h(b)
+            };
+            same("railway!", railway, chain)?;
+
+            let n = gen.int(-1000, 1000);
+            let option = Some(n).bind(|n| validate_positive(n).ok());
+            same(
+                "option",
+                option.or_fail(()),
+                validate_positive(n).map_err(|_| ()),
+            )
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn the_harness_reports_a_reproducible_counterexample() {
+        let failure = check("small", CASES, |gen| match gen.int(0, 9) {
+            7 => Err("seven".to_string()),
+            _ => Ok(()),
+        })
+        .unwrap_err();
+        assert!(failure.starts_with("small failed on case "), "{failure}");
+        assert!(failure.ends_with(": seven"));
+        let a: Vec<_> = (0..5)
+            .map({
+                let mut gen = Gen::new(42);
+                move |_| gen.string(8)
+            })
+            .collect();
+        let mut gen = Gen::new(42);
+        let b: Vec<_> = (0..5).map(|_| gen.string(8)).collect();
+        assert_eq!(a, b);
+        assert!((0..1000).all(|_| (-3..=3).contains(&gen.int(-3, 3))));
+        assert_eq!(gen.int(5, 5), 5);
+        let full = gen.int(i32::MIN, i32::MAX);
+        assert!((i32::MIN..=i32::MAX).contains(&full));
+    }
+
+    #[test]
+    #[should_panic(expected = "Gen::int needs low <= high, got 1..=0")]
+    fn an_empty_range_is_a_clear_error() {
+        Gen::new(1).int(1, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Gen::pick needs at least one choice")]
+    fn picking_from_no_choices_is_a_clear_error() {
+        Gen::new(1).pick::<i32>(&[]);
+    }
+}
@@ -0,0 +1,327 @@
+//! Examples from `traits-functional/MonadicResult.md`.
+//!
+//! The regions between `// ANCHOR:` comments are included in the chapter.
+
+pub mod async_railway;
+pub mod context;
+pub mod control;
+pub mod convert;
+pub mod fork;
+pub mod iter;
+pub mod kleisli;
+pub mod laws;
+pub mod option;
+pub mod pipeline;
+pub mod railway;
+pub mod retry;
+pub mod saga;
+pub mod trace;
+pub mod validated;
+
+use std::fmt;
+
+// ANCHOR: monadic_result
+// This is a custom trait that needs to be defined in your codebase.
+// Its methods are named so that none of them is shadowed by an inherent
+// method of `Result`: `result.and_then(f)` would always call std's version.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a `Result`, so it cannot be a step of the railway",
+    label = "this step must return a `Result`"
+)]
+pub trait MonadicResult<T, E> {
+    /// Runs `f` on the success value; an error skips `f` and is kept.
+    fn bind<U, F>(self, f: F) -> Result<U, E>
+    where
+        F: FnOnce(T) -> Result<U, E>;
+
+    /// Another name for [`bind`](MonadicResult::bind).
+    fn flat_map<U, F>(self, f: F) -> Result<U, E>
+    where
+        Self: Sized,
+        F: FnOnce(T) -> Result<U, E>,
+    {
+        self.bind(f)
+    }
+
+    /// Transforms the success value with a step that cannot fail.
+    fn fmap<U, F>(self, f: F) -> Result<U, E>
+    where
+        F: FnOnce(T) -> U;
+
+    /// Transforms the error, e.g. into the error type of the next step.
+    fn fmap_err<E2, F>(self, f: F) -> Result<T, E2>
+    where
+        F: FnOnce(E) -> E2;
+
+    /// Gives an error a second chance: `fallback` may recover with a value
+    /// or fail with an error of its own. A success skips `fallback`.
+    fn or_else_try<E2, F>(self, fallback: F) -> Result<T, E2>
+    where
+        F: FnOnce(E) -> Result<T, E2>;
+
+    /// Looks at the success value without changing the result.
+    fn tap<F>(self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&T);
+
+    /// Looks at the error without changing the result.
+    fn tap_err<F>(self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&E);
+}
+
+impl<T, E> MonadicResult<T, E> for Result<T, E> {
+    fn bind<U, F>(self, f: F) -> Result<U, E>
+    where
+        F: FnOnce(T) -> Result<U, E>,
+    {
+        match self {
+            Ok(value) => f(value),
+            Err(error) => Err(error), // Early return on error
+        }
+    }
+
+    fn fmap<U, F>(self, f: F) -> Result<U, E>
+    where
+        F: FnOnce(T) -> U,
+    {
+        self.bind(|value| Ok(f(value)))
+    }
+
+    fn fmap_err<E2, F>(self, f: F) -> Result<T, E2>
+    where
+        F: FnOnce(E) -> E2,
+    {
+        match self {
+            Ok(value) => Ok(value),
+            Err(error) => Err(f(error)),
+        }
+    }
+
+    fn or_else_try<E2, F>(self, fallback: F) -> Result<T, E2>
+    where
+        F: FnOnce(E) -> Result<T, E2>,
+    {
+        match self {
+            Ok(value) => Ok(value),
+            Err(error) => fallback(error), // Switch back to the success track
+        }
+    }
+
+    fn tap<F>(self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&T),
+    {
+        if let Ok(value) = &self {
+            f(value);
+        }
+        self
+    }
+
+    fn tap_err<F>(self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&E),
+    {
+        if let Err(error) = &self {
+            f(error);
+        }
+        self
+    }
+}
+// ANCHOR_END: monadic_result
+
+// ANCHOR: early_return_steps
+pub fn validate_positive(x: i32) -> Result<i32, String> {
+    if x > 0 {
+        Ok(x)
+    } else {
+        Err("Number must be positive".to_string())
+    }
+}
+
+pub fn double(x: i32) -> Result<i32, String> {
+    Ok(x * 2)
+}
+// ANCHOR_END: early_return_steps
+
+// ANCHOR: encrypted_data
+#[derive(Debug)]
+pub struct EncryptedData {
+    pub content: String,
+    pub key: i32,
+}
+
+// All functions follow the same signature: fn(EncryptedData) -> Result<EncryptedData, ProcessError>
+pub type ProcessResult = Result<EncryptedData, ProcessError>;
+
+#[derive(Debug)]
+pub enum ProcessError {
+    InvalidInput,
+    ProcessingFailed,
+    ValidationFailed,
+}
+
+// A std error, so that it can be the source of another error
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ProcessError::InvalidInput => "invalid input",
+            ProcessError::ProcessingFailed => "processing failed",
+            ProcessError::ValidationFailed => "validation failed",
+        };
+        f.write_str(text)
+    }
+}
+
+impl std::error::Error for ProcessError {}
+
+pub fn validate_data(data: EncryptedData) -> ProcessResult {
+    if data.content.is_empty() {
+        Err(ProcessError::InvalidInput)
+    } else {
+        Ok(data)
+    }
+}
+
+pub fn apply_encryption(data: EncryptedData) -> ProcessResult {
+    Ok(EncryptedData {
+        content: format!("encrypted_{}", data.content),
+        key: data.key,
+    })
+}
+
+pub fn add_checksum(data: EncryptedData) -> ProcessResult {
+    Ok(EncryptedData {
+        content: format!("{}_checksum", data.content),
+        key: data.key + 1,
+    })
+}
+
+pub fn finalize_process(data: EncryptedData) -> ProcessResult {
+    Ok(EncryptedData {
+        content: format!("final_{}", data.content),
+        key: data.key,
+    })
+}
+// ANCHOR_END: encrypted_data
+
+// ANCHOR: changing_types
+pub fn parse_data(input: String) -> ProcessResult {
+    match input.split_once(':') {
+        Some((content, key)) => {
+            let key = key.parse().map_err(|_| ProcessError::InvalidInput)?;
+            Ok(EncryptedData {
+                content: content.to_string(),
+                key,
+            })
+        }
+        None => Err(ProcessError::InvalidInput),
+    }
+}
+
+pub fn to_bytes(data: EncryptedData) -> Result<Vec<u8>, ProcessError> {
+    if data.key < 0 {
+        return Err(ProcessError::ValidationFailed);
+    }
+    Ok(data.content.into_bytes())
+}
+// ANCHOR_END: changing_types
+
+/// Test input, shared by the tests of every module.
+#[cfg(test)]
+pub(crate) fn data(content: &str, key: i32) -> EncryptedData {
+    EncryptedData {
+        content: content.to_string(),
+        key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_runs_the_step_on_ok() {
+        let result = Ok(5).bind(validate_positive).bind(double);
+        assert_eq!(result, Ok(10));
+    }
+
+    #[test]
+    fn bind_skips_the_step_on_err() {
+        let mut called = false;
+        let result = validate_positive(-5).bind(|x| {
+            called = true;
+            double(x)
+        });
+        assert_eq!(result, Err("Number must be positive".to_string()));
+        assert!(!called);
+    }
+
+    #[test]
+    fn bind_changes_the_success_type() {
+        let result = Ok("secret:7".to_string())
+            .bind(parse_data)
+            .flat_map(validate_data)
+            .bind(to_bytes)
+            .fmap(|bytes| bytes.len());
+        assert_eq!(result.unwrap(), 6);
+    }
+
+    #[test]
+    fn type_changing_chain_keeps_the_first_error() {
+        let mut steps = Vec::new();
+        let result = Ok(":1".to_string())
+            .bind(parse_data)
+            .tap(|_| steps.push("parsed"))
+            .bind(validate_data)
+            .tap(|_| steps.push("validated"))
+            .bind(to_bytes)
+            .fmap(|bytes| {
+                steps.push("measured");
+                bytes.len()
+            });
+        assert!(matches!(result, Err(ProcessError::InvalidInput)));
+        assert_eq!(steps, vec!["parsed"]);
+    }
+
+    #[test]
+    fn fmap_err_converts_only_errors() {
+        let ok: Result<i32, String> = Ok(1).fmap_err(|error: i32| error.to_string());
+        assert_eq!(ok, Ok(1));
+        let err = validate_positive(0).fmap_err(|error| error.len());
+        assert_eq!(err, Err(23));
+    }
+
+    #[test]
+    fn taps_see_the_value_without_changing_it() {
+        let mut seen = Vec::new();
+        let result = Ok(5)
+            .tap(|x| seen.push(format!("ok {x}")))
+            .tap_err(|e: &String| seen.push(format!("err {e}")))
+            .bind(validate_positive);
+        assert_eq!(result, Ok(5));
+        let result = validate_positive(-1)
+            .tap(|x| seen.push(format!("ok {x}")))
+            .tap_err(|e| seen.push(format!("err {e}")));
+        assert!(result.is_err());
+        assert_eq!(seen, vec!["ok 5", "err Number must be positive"]);
+    }
+
+    #[test]
+    fn pipeline_applies_every_step_in_order() {
+        let result = Ok(data("secret", 123))
+            .bind(validate_data)
+            .bind(apply_encryption)
+            .bind(add_checksum)
+            .bind(finalize_process)
+            .unwrap();
+        assert_eq!(result.content, "final_encrypted_secret_checksum");
+        assert_eq!(result.key, 124);
+    }
+
+    #[test]
+    fn pipeline_stops_at_invalid_input() {
+        let result = Ok(data("", 123)).bind(validate_data).bind(apply_encryption);
+        assert!(matches!(result, Err(ProcessError::InvalidInput)));
+    }
+}
@@ -0,0 +1,129 @@
+//! The railway for `Option` chains.
+
+// ANCHOR: monadic_option
+// `Option` has inherent `and_then` and `map` methods too, so the same names
+// as `MonadicResult` are used
+pub trait MonadicOption<T> {
+    /// Runs `f` on the value; `None` skips `f`.
+    fn bind<U, F>(self, f: F) -> Option<U>
+    where
+        F: FnOnce(T) -> Option<U>;
+
+    fn fmap<U, F>(self, f: F) -> Option<U>
+    where
+        F: FnOnce(T) -> U;
+
+    fn tap<F>(self, f: F) -> Option<T>
+    where
+        F: FnOnce(&T);
+
+    /// Moves onto the `Result` railway, with `error` for a missing value.
+    fn or_fail<E>(self, error: E) -> Result<T, E>;
+
+    /// Like `or_fail`, but the error is only built when the value is missing.
+    fn or_fail_with<E, F>(self, error: F) -> Result<T, E>
+    where
+        F: FnOnce() -> E;
+}
+
+impl<T> MonadicOption<T> for Option<T> {
+    fn bind<U, F>(self, f: F) -> Option<U>
+    where
+        F: FnOnce(T) -> Option<U>,
+    {
+        match self {
+            Some(value) => f(value),
+            None => None, // Early return on a missing value
+        }
+    }
+
+    fn fmap<U, F>(self, f: F) -> Option<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        self.bind(|value| Some(f(value)))
+    }
+
+    fn tap<F>(self, f: F) -> Option<T>
+    where
+        F: FnOnce(&T),
+    {
+        if let Some(value) = &self {
+            f(value);
+        }
+        self
+    }
+
+    fn or_fail<E>(self, error: E) -> Result<T, E> {
+        self.or_fail_with(|| error)
+    }
+
+    fn or_fail_with<E, F>(self, error: F) -> Result<T, E>
+    where
+        F: FnOnce() -> E,
+    {
+        match self {
+            Some(value) => Ok(value),
+            None => Err(error()),
+        }
+    }
+}
+// ANCHOR_END: monadic_option
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MonadicResult, ProcessError};
+
+    fn first_word(text: &str) -> Option<&str> {
+        text.split_whitespace().next()
+    }
+
+    fn initial(word: &str) -> Option<char> {
+        word.chars().next()
+    }
+
+    #[test]
+    fn bind_stops_at_the_first_none() {
+        assert_eq!(
+            Some("hello world").bind(first_word).bind(initial),
+            Some('h')
+        );
+        let mut called = false;
+        let missing = Some("   ").bind(first_word).bind(|word| {
+            called = true;
+            initial(word)
+        });
+        assert_eq!(missing, None);
+        assert!(!called);
+    }
+
+    #[test]
+    fn fmap_and_tap() {
+        let mut seen = None;
+        let length = Some("secret")
+            .tap(|text| seen = Some(text.len()))
+            .fmap(str::len);
+        assert_eq!(length, Some(6));
+        assert_eq!(seen, Some(6));
+        assert_eq!(None::<&str>.fmap(str::len), None);
+    }
+
+    #[test]
+    fn converts_to_the_result_railway() {
+        let key = Some("key=7")
+            .bind(|pair| pair.split_once('='))
+            .or_fail(ProcessError::InvalidInput)
+            .bind(|(_, value)| value.parse::<i32>().map_err(|_| ProcessError::InvalidInput));
+        assert_eq!(key.unwrap(), 7);
+
+        let mut built = false;
+        let missing = None::<i32>.or_fail_with(|| {
+            built = true;
+            ProcessError::ValidationFailed
+        });
+        assert!(matches!(missing, Err(ProcessError::ValidationFailed)));
+        assert!(built);
+        assert_eq!(Some(1).or_fail("unused"), Ok(1));
+    }
+}
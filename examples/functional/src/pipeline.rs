@@ -0,0 +1,150 @@
+//! A reusable pipeline of named steps.
+
+// ANCHOR: pipeline
+use std::fmt;
+use std::sync::Arc;
+
+/// A step of a pipeline: takes the value and either passes it on or fails.
+type StepFn<T, E> = Arc<dyn Fn(T) -> Result<T, E> + Send + Sync>;
+
+/// A sequence of named steps run one after another on the same type.
+pub struct Pipeline<T, E> {
+    steps: Vec<(&'static str, StepFn<T, E>)>,
+}
+
+/// The error of a failed pipeline run: the step that failed and its error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepError<E> {
+    pub step: &'static str,
+    pub error: E,
+}
+
+impl<T, E> Pipeline<T, E> {
+    pub fn new() -> Self {
+        Pipeline { steps: Vec::new() }
+    }
+
+    /// Adds a step at the end of the pipeline.
+    pub fn step<F>(mut self, name: &'static str, f: F) -> Self
+    where
+        F: Fn(T) -> Result<T, E> + Send + Sync + 'static,
+    {
+        self.steps.push((name, Arc::new(f)));
+        self
+    }
+
+    /// Runs every step in order. The first failing step stops the run,
+    /// and its name is returned with the error.
+    pub fn run(&self, input: T) -> Result<T, StepError<E>> {
+        self.steps
+            .iter()
+            .try_fold(input, |value, &(name, ref step)| {
+                step(value).map_err(|error| StepError { step: name, error })
+            })
+    }
+
+    /// The names of the steps, in order.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.steps.iter().map(|(name, _)| *name).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl<T, E> Default for Pipeline<T, E> {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
+
+// Cloning shares the steps, so `T` and `E` need not be `Clone`.
+impl<T, E> Clone for Pipeline<T, E> {
+    fn clone(&self) -> Self {
+        Pipeline {
+            steps: self.steps.clone(),
+        }
+    }
+}
+
+impl<T, E> fmt::Debug for Pipeline<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("steps", &self.names())
+            .finish()
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for StepError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step `{}` failed: {:?}", self.step, self.error)
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for StepError<E> {}
+// ANCHOR_END: pipeline
+
+impl<T, E> Pipeline<T, E> {
+    pub(crate) fn steps(&self) -> &[(&'static str, StepFn<T, E>)] {
+        &self.steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn encryption() -> Pipeline<EncryptedData, ProcessError> {
+        Pipeline::new()
+            .step("validate", validate_data)
+            .step("encrypt", apply_encryption)
+            .step("checksum", add_checksum)
+            .step("finalize", finalize_process)
+    }
+
+    #[test]
+    fn runs_the_steps_in_order() {
+        let result = encryption().run(data("secret", 123)).unwrap();
+        assert_eq!(result.content, "final_encrypted_secret_checksum");
+        assert_eq!(result.key, 124);
+    }
+
+    #[test]
+    fn reports_the_step_that_failed() {
+        let error = encryption().run(data("", 123)).unwrap_err();
+        assert_eq!(error.step, "validate");
+        assert!(matches!(error.error, ProcessError::InvalidInput));
+        assert_eq!(error.to_string(), "step `validate` failed: InvalidInput");
+    }
+
+    #[test]
+    fn stops_at_the_first_failure() {
+        let pipeline = Pipeline::new()
+            .step("validate", validate_positive)
+            .step("fail", |_| Err("failed".to_string()))
+            .step("double", |_| panic!("runs after a failure"));
+        let error = pipeline.run(1).unwrap_err();
+        assert_eq!(error.step, "fail");
+    }
+
+    #[test]
+    fn is_reusable_and_clonable() {
+        let pipeline = Pipeline::new()
+            .step("validate", validate_positive)
+            .step("double", double);
+        let copy = pipeline.clone().step("double again", double);
+        assert_eq!(pipeline.run(2), Ok(4));
+        assert_eq!(pipeline.run(5), Ok(10));
+        assert_eq!(copy.run(5), Ok(20));
+        assert_eq!(pipeline.names(), vec!["validate", "double"]);
+        assert_eq!(copy.len(), 3);
+        assert!(Pipeline::<i32, String>::default().is_empty());
+        assert_eq!(Pipeline::<i32, String>::new().run(7), Ok(7));
+    }
+}
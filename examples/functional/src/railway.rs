@@ -0,0 +1,146 @@
+//! Do-notation for `MonadicResult` chains.
+//!
+//! ```
+//! use traits_functional::railway;
+//!
+//! fn half(x: i32) -> Result<i32, String> {
+//!     match x % 2 {
+//!         0 => Ok(x / 2),
+//!         _ => Err(format!("{x} is odd")),
+//!     }
+//! }
+//!
+//! let sum = railway! {
+//!     let a <- half(20);
+//!     let b <- half(a);
+//!     ret a + b
+//! };
+//! assert_eq!(sum, Ok(15));
+//! assert_eq!(railway! { let a <- half(3); ret a }, Err("3 is odd".to_string()));
+//! ```
+//!
+//! Misuse is a compile error with a message saying what is expected, as in
+//! the blocks below. Doctests only check that they fail to compile; the
+//! messages themselves are compared with `tests/ui/railway`.
+//!
+//! ```compile_fail
+//! // error: `railway!` needs at least a final `ret EXPR`
+//! let empty: Result<i32, String> = traits_functional::railway! {};
+//! ```
+//!
+//! ```compile_fail
+//! // error: `railway!` must end with `ret EXPR` or a step, not with a binding
+//! let unfinished: Result<i32, String> = traits_functional::railway! {
+//!     let a <- Ok(1);
+//! };
+//! ```
+//!
+//! ```compile_fail
+//! // error: `ret` must be the last statement of `railway!`
+//! let early: Result<i32, String> = traits_functional::railway! {
+//!     ret 1;
+//!     let a <- Ok(2);
+//! };
+//! ```
+//!
+//! ```compile_fail
+//! // error: a binding needs `let`: write `let NAME <- STEP;`
+//! let arrow: Result<i32, String> = traits_functional::railway! {
+//!     a <- Ok(1);
+//!     ret a
+//! };
+//! ```
+//!
+//! ```compile_fail
+//! // error[E0277]: `{integer}` is not a `Result`, so it cannot be a step of the railway
+//! let not_a_result: Result<i32, String> = traits_functional::railway! {
+//!     let a <- 1;
+//!     ret a
+//! };
+//! ```
+
+// ANCHOR: railway
+/// Chains steps that need the values of earlier steps:
+///
+/// - `let NAME <- STEP;` binds the success value of `STEP`, or stops with its error
+/// - `let NAME = EXPR;` is a plain `let` for values that cannot fail
+/// - `ret EXPR` ends the chain with `Ok(EXPR)`; a final step without `ret`
+///   is returned as it is
+#[macro_export]
+macro_rules! railway {
+    () => {
+        compile_error!("`railway!` needs at least a final `ret EXPR`")
+    };
+    (ret $value:expr $(;)?) => {
+        ::core::result::Result::Ok($value)
+    };
+    (ret $value:expr; $($rest:tt)+) => {
+        compile_error!("`ret` must be the last statement of `railway!`")
+    };
+    (let $name:tt <- $step:expr $(;)?) => {
+        compile_error!("`railway!` must end with `ret EXPR` or a step, not with a binding")
+    };
+    (let $name:tt <- $step:expr; $($rest:tt)+) => {
+        $crate::MonadicResult::bind($step, |$name| $crate::railway!($($rest)+))
+    };
+    ($name:tt <- $($rest:tt)*) => {
+        compile_error!("a binding needs `let`: write `let NAME <- STEP;`")
+    };
+    (let $name:tt = $value:expr; $($rest:tt)+) => {{
+        let $name = $value;
+        $crate::railway!($($rest)+)
+    }};
+    ($step:expr) => {
+        $step
+    };
+    ($($other:tt)*) => {
+        compile_error!("expected `let NAME <- STEP;`, `let NAME = EXPR;`, `ret EXPR` or a final step")
+    };
+}
+// ANCHOR_END: railway
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn key(input: &str) -> Result<i32, ProcessError> {
+        input.parse().map_err(|_| ProcessError::InvalidInput)
+    }
+
+    #[test]
+    fn binds_values_for_later_steps() {
+        let result = railway! {
+            let key <- key("7");
+            let data <- validate_data(EncryptedData { content: "secret".into(), key });
+            let data <- apply_encryption(data);
+            let label = format!("{}#{}", data.content, key);
+            ret (label, data.key)
+        };
+        assert_eq!(result.unwrap(), ("encrypted_secret#7".to_string(), 7));
+    }
+
+    #[test]
+    fn stops_at_the_first_error() {
+        let mut reached = false;
+        let result: Result<i32, ProcessError> = railway! {
+            let key <- key("x");
+            let _ <- {
+                reached = true;
+                Ok(key)
+            };
+            ret key
+        };
+        assert!(matches!(result, Err(ProcessError::InvalidInput)));
+        assert!(!reached);
+    }
+
+    #[test]
+    fn ends_with_a_step_or_a_destructuring_binding() {
+        let result = railway! {
+            let (a, b) <- Ok::<_, String>((2, 3));
+            let _ <- validate_positive(a);
+            double(a * b)
+        };
+        assert_eq!(result, Ok(12));
+    }
+}
@@ -0,0 +1,255 @@
+//! Retrying steps that fail transiently.
+
+// ANCHOR: retry
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait before each retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    None,
+    Fixed(Duration),
+    /// Starts at `initial` and doubles on every retry, up to `max`.
+    Exponential {
+        initial: Duration,
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// The wait before retry number `retry`, counting from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            Backoff::None => Duration::ZERO,
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => initial
+                .checked_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+                .map_or(max, |delay| delay.min(max)),
+        }
+    }
+}
+
+/// Waits between attempts. Tests use a clock that only records the waits.
+pub trait Clock: Send + Sync {
+    fn sleep(&self, duration: Duration);
+}
+
+/// Really sleeps.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Records the waits without sleeping.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+impl ManualClock {
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+}
+
+impl Clock for ManualClock {
+    fn sleep(&self, duration: Duration) {
+        self.sleeps.lock().unwrap().push(duration);
+    }
+}
+
+/// When and how often to retry a failed step.
+pub struct RetryPolicy<E> {
+    retries: u32,
+    backoff: Backoff,
+    clock: Arc<dyn Clock>,
+    should_retry: Arc<dyn Fn(&E) -> bool + Send + Sync>,
+}
+
+impl<E> RetryPolicy<E> {
+    /// Retries up to `retries` times after the first attempt, whatever the
+    /// error, sleeping on the system clock.
+    pub fn new(retries: u32, backoff: Backoff) -> Self {
+        RetryPolicy {
+            retries,
+            backoff,
+            clock: Arc::new(SystemClock),
+            should_retry: Arc::new(|_| true),
+        }
+    }
+
+    /// Only retries errors for which `predicate` holds; others are returned
+    /// at once.
+    pub fn when<P>(mut self, predicate: P) -> Self
+    where
+        P: Fn(&E) -> bool + Send + Sync + 'static,
+    {
+        self.should_retry = Arc::new(predicate);
+        self
+    }
+
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+pub trait RetryStep<T, U, E>: Fn(T) -> Result<U, E> + Sized {
+    /// This step, run again on a copy of its input when it fails.
+    fn retry(self, retries: u32, backoff: Backoff) -> impl Fn(T) -> Result<U, E> {
+        self.retry_with(RetryPolicy::new(retries, backoff))
+    }
+
+    /// Like `retry`, with a predicate or clock set on the policy.
+    fn retry_with(self, policy: RetryPolicy<E>) -> impl Fn(T) -> Result<U, E>;
+}
+
+impl<T, U, E, F> RetryStep<T, U, E> for F
+where
+    T: Clone,
+    F: Fn(T) -> Result<U, E>,
+{
+    fn retry_with(self, policy: RetryPolicy<E>) -> impl Fn(T) -> Result<U, E> {
+        move |input: T| {
+            let mut retry = 0;
+            loop {
+                match self(input.clone()) {
+                    Err(error) if retry < policy.retries && (policy.should_retry)(&error) => {
+                        retry += 1;
+                        policy.clock.sleep(policy.backoff.delay(retry));
+                    }
+                    result => return result,
+                }
+            }
+        }
+    }
+}
+// ANCHOR_END: retry
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EncryptedData, MonadicResult, ProcessError, ProcessResult};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// A step failing with each of `errors` in turn, then succeeding.
+    fn flaky(
+        errors: Vec<ProcessError>,
+    ) -> (Arc<AtomicU32>, impl Fn(i32) -> Result<i32, ProcessError>) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let errors = Mutex::new(errors.into_iter());
+        let step = move |x| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            match errors.lock().unwrap().next() {
+                Some(error) => Err(error),
+                None => Ok(x + 1),
+            }
+        };
+        (calls, step)
+    }
+
+    fn transient(error: &ProcessError) -> bool {
+        matches!(error, ProcessError::ProcessingFailed)
+    }
+
+    #[test]
+    fn backoff_delays() {
+        let exponential = Backoff::Exponential {
+            initial: 10 * MS,
+            max: 50 * MS,
+        };
+        let delays: Vec<_> = (1..=5).map(|retry| exponential.delay(retry)).collect();
+        assert_eq!(delays, vec![10 * MS, 20 * MS, 40 * MS, 50 * MS, 50 * MS]);
+        assert_eq!(exponential.delay(100), 50 * MS);
+        assert_eq!(Backoff::Fixed(MS).delay(3), MS);
+        assert_eq!(Backoff::None.delay(1), Duration::ZERO);
+    }
+
+    #[test]
+    fn retries_until_the_step_succeeds() {
+        let clock = Arc::new(ManualClock::default());
+        let failures = vec![
+            ProcessError::ProcessingFailed,
+            ProcessError::ProcessingFailed,
+        ];
+        let (calls, step) = flaky(failures);
+        let backoff = Backoff::Exponential {
+            initial: 100 * MS,
+            max: 1000 * MS,
+        };
+        let step = step.retry_with(RetryPolicy::new(3, backoff).clock(clock.clone()));
+        assert_eq!(step(1).unwrap(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(clock.sleeps(), vec![100 * MS, 200 * MS]);
+    }
+
+    #[test]
+    fn gives_up_after_the_last_retry() {
+        let clock = Arc::new(ManualClock::default());
+        let (calls, step) = flaky((0..5).map(|_| ProcessError::ProcessingFailed).collect());
+        let policy = RetryPolicy::new(2, Backoff::Fixed(MS)).clock(clock.clone());
+        let step = step.retry_with(policy);
+        assert!(matches!(step(1), Err(ProcessError::ProcessingFailed)));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(clock.sleeps(), vec![MS, MS]);
+    }
+
+    #[test]
+    fn predicate_decides_which_errors_are_retried() {
+        let clock = Arc::new(ManualClock::default());
+        let (calls, step) = flaky(vec![
+            ProcessError::ProcessingFailed,
+            ProcessError::ValidationFailed,
+        ]);
+        let policy = RetryPolicy::new(5, Backoff::Fixed(MS))
+            .when(transient)
+            .clock(clock.clone());
+        let step = step.retry_with(policy);
+        assert!(matches!(step(1), Err(ProcessError::ValidationFailed)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(clock.sleeps(), vec![MS]);
+    }
+
+    #[test]
+    fn retried_steps_fit_in_a_chain() {
+        let (_, step) = flaky(vec![ProcessError::ProcessingFailed]);
+        let step = step.retry(1, Backoff::None);
+        assert_eq!(Ok(1).bind(&step).bind(|x| Ok(x * 10)).unwrap(), 20);
+    }
+
+    #[test]
+    fn or_else_try_recovers_or_replaces_the_error() {
+        fn cached(_: ProcessError) -> ProcessResult {
+            Ok(EncryptedData {
+                content: "cached".to_string(),
+                key: 0,
+            })
+        }
+        let empty = EncryptedData {
+            content: String::new(),
+            key: 1,
+        };
+        let recovered = crate::validate_data(empty).or_else_try(cached);
+        assert_eq!(recovered.unwrap().content, "cached");
+
+        let replaced: Result<i32, String> =
+            Err(ProcessError::InvalidInput).or_else_try(|error| Err(format!("{error:?}")));
+        assert_eq!(replaced, Err("InvalidInput".to_string()));
+
+        let mut called = false;
+        let kept = Ok::<_, ProcessError>(5).or_else_try(|_| {
+            called = true;
+            Ok::<_, ProcessError>(0)
+        });
+        assert_eq!(kept.unwrap(), 5);
+        assert!(!called);
+    }
+}
@@ -0,0 +1,240 @@
+//! Undoing the side effects of earlier steps when a later step fails.
+
+use crate::pipeline::StepError;
+
+// ANCHOR: saga
+use std::fmt;
+use std::sync::Arc;
+
+/// Undoes the side effects of a step.
+type Compensation<E> = Box<dyn FnOnce() -> Result<(), E>>;
+
+/// The compensations registered so far by the steps of a saga run.
+pub struct Compensations<E> {
+    step: &'static str,
+    registered: Vec<(&'static str, Compensation<E>)>,
+}
+
+impl<E> Compensations<E> {
+    /// Registers `undo` to run if this step or a later one fails.
+    pub fn register<F>(&mut self, undo: F)
+    where
+        F: FnOnce() -> Result<(), E> + 'static,
+    {
+        self.registered.push((self.step, Box::new(undo)));
+    }
+
+    /// Runs the compensations, last registered first. A failing
+    /// compensation does not stop the others; its error is kept.
+    fn unwind(self) -> Vec<StepError<E>> {
+        self.registered
+            .into_iter()
+            .rev()
+            .filter_map(|(step, undo)| undo().err().map(|error| StepError { step, error }))
+            .collect()
+    }
+}
+
+type SagaStepFn<T, E> = Arc<dyn Fn(T, &mut Compensations<E>) -> Result<T, E> + Send + Sync>;
+
+/// A pipeline whose steps can register how to undo what they did.
+pub struct Saga<T, E> {
+    steps: Vec<(&'static str, SagaStepFn<T, E>)>,
+}
+
+/// The error of a failed saga run: the step that failed, and every
+/// compensation that failed while undoing the steps before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SagaError<E> {
+    pub failed: StepError<E>,
+    /// In the order the compensations ran.
+    pub compensations: Vec<StepError<E>>,
+}
+
+impl<T, E> Saga<T, E> {
+    pub fn new() -> Self {
+        Saga { steps: Vec::new() }
+    }
+
+    /// Adds a step with nothing to undo.
+    pub fn step<F>(self, name: &'static str, f: F) -> Self
+    where
+        F: Fn(T) -> Result<T, E> + Send + Sync + 'static,
+    {
+        self.compensated(name, move |value, _| f(value))
+    }
+
+    /// Adds a step that registers compensations for its side effects.
+    pub fn compensated<F>(mut self, name: &'static str, f: F) -> Self
+    where
+        F: Fn(T, &mut Compensations<E>) -> Result<T, E> + Send + Sync + 'static,
+    {
+        self.steps.push((name, Arc::new(f)));
+        self
+    }
+
+    /// Runs every step in order. When a step fails, the compensations
+    /// registered so far run in reverse order, including those the failing
+    /// step registered before it failed.
+    pub fn run(&self, input: T) -> Result<T, SagaError<E>> {
+        let mut compensations = Compensations {
+            step: "",
+            registered: Vec::new(),
+        };
+        let mut value = input;
+        for &(name, ref step) in &self.steps {
+            compensations.step = name;
+            value = match step(value, &mut compensations) {
+                Ok(value) => value,
+                Err(error) => {
+                    return Err(SagaError {
+                        failed: StepError { step: name, error },
+                        compensations: compensations.unwind(),
+                    })
+                }
+            };
+        }
+        Ok(value)
+    }
+
+    /// The names of the steps in the order they run; their compensations
+    /// run the other way round.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.steps.iter().map(|(name, _)| *name).collect()
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for SagaError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.failed)?;
+        for undo in &self.compensations {
+            write!(f, "; undoing `{}` failed: {:?}", undo.step, undo.error)?;
+        }
+        Ok(())
+    }
+}
+
+// The message already names the failed step, so it is not a source too
+impl<E: fmt::Debug> std::error::Error for SagaError<E> {}
+// ANCHOR_END: saga
+
+impl<T, E> Default for Saga<T, E> {
+    fn default() -> Self {
+        Saga::new()
+    }
+}
+
+impl<T, E> fmt::Debug for Saga<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Saga")
+            .field("steps", &self.names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ResultContext;
+    use crate::*;
+    use std::sync::Mutex;
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    /// A step that logs itself and registers a compensation logging its undo.
+    fn logged(
+        log: &Log,
+        name: &'static str,
+    ) -> impl Fn(i32, &mut Compensations<String>) -> Result<i32, String> {
+        let log = log.clone();
+        move |x, undo| {
+            log.lock().unwrap().push(format!("do {name}"));
+            let log = log.clone();
+            undo.register(move || {
+                log.lock().unwrap().push(format!("undo {name}"));
+                Ok(())
+            });
+            Ok(x + 1)
+        }
+    }
+
+    fn entries(log: &Log) -> Vec<String> {
+        log.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn success_runs_no_compensation() {
+        let log = Log::default();
+        let saga = Saga::new()
+            .compensated("a", logged(&log, "a"))
+            .step("double", double)
+            .compensated("b", logged(&log, "b"));
+        assert_eq!(saga.run(1), Ok(5));
+        assert_eq!(entries(&log), vec!["do a", "do b"]);
+        assert_eq!(saga.names(), vec!["a", "double", "b"]);
+    }
+
+    #[test]
+    fn failure_undoes_earlier_steps_in_reverse_order() {
+        let log = Log::default();
+        let saga = Saga::new()
+            .compensated("a", logged(&log, "a"))
+            .compensated("b", logged(&log, "b"))
+            .step("fail", |_| Err("disk full".to_string()))
+            .compensated("c", logged(&log, "c"));
+        let error = saga.run(1).unwrap_err();
+        assert_eq!(error.failed.step, "fail");
+        assert!(error.compensations.is_empty());
+        assert_eq!(entries(&log), vec!["do a", "do b", "undo b", "undo a"]);
+        assert_eq!(error.to_string(), "step `fail` failed: \"disk full\"");
+    }
+
+    #[test]
+    fn failing_step_undoes_what_it_registered() {
+        let log = Log::default();
+        let saga = Saga::new().compensated("partial", {
+            let log = log.clone();
+            move |_: i32, undo: &mut Compensations<String>| {
+                let log = log.clone();
+                undo.register(move || {
+                    log.lock().unwrap().push("undo partial".to_string());
+                    Ok(())
+                });
+                Err("half done".to_string())
+            }
+        });
+        assert!(saga.run(1).is_err());
+        assert_eq!(entries(&log), vec!["undo partial"]);
+    }
+
+    #[test]
+    fn reports_compensation_errors_and_keeps_undoing() {
+        let log = Log::default();
+        let saga = Saga::new()
+            .compensated("a", logged(&log, "a"))
+            .compensated("b", |x, undo| {
+                undo.register(|| Err("b is gone".to_string()));
+                Ok(x)
+            })
+            .step("validate", |x| validate_positive(-x));
+        let error = saga.run(1).unwrap_err();
+        assert_eq!(
+            error.compensations,
+            vec![StepError {
+                step: "b",
+                error: "b is gone".to_string()
+            }]
+        );
+        assert_eq!(entries(&log), vec!["do a", "undo a"]);
+        assert_eq!(
+            error.to_string(),
+            "step `validate` failed: \"Number must be positive\"; undoing `b` failed: \"b is gone\""
+        );
+        assert!(std::error::Error::source(&error).is_none());
+        let report = Err::<i32, _>(error)
+            .context("order", "placing the order")
+            .unwrap_err()
+            .report();
+        assert_eq!(report.matches("Number must be positive").count(), 1);
+    }
+}
@@ -0,0 +1,228 @@
+//! Opt-in tracing of the steps of a chain or pipeline.
+
+use crate::pipeline::{Pipeline, StepError};
+
+// ANCHOR: trace
+use std::fmt;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// What happened to one step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// The step failed; the `Debug` output of its error.
+    Failed(String),
+    /// An earlier step failed, so this one never ran.
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepEvent {
+    pub step: &'static str,
+    pub duration: Duration,
+    pub outcome: Outcome,
+}
+
+/// Where step events go.
+pub trait EventSink {
+    fn record(&mut self, event: StepEvent);
+}
+
+/// Keeps every event, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub events: Vec<StepEvent>,
+}
+
+impl EventSink for MemorySink {
+    fn record(&mut self, event: StepEvent) {
+        self.events.push(event);
+    }
+}
+
+/// Writes each event as one line of JSON.
+pub struct JsonLines<W> {
+    out: W,
+}
+
+impl JsonLines<io::Stdout> {
+    pub fn stdout() -> Self {
+        JsonLines { out: io::stdout() }
+    }
+}
+
+impl<W: Write> JsonLines<W> {
+    pub fn new(out: W) -> Self {
+        JsonLines { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> EventSink for JsonLines<W> {
+    fn record(&mut self, event: StepEvent) {
+        let (outcome, error) = match &event.outcome {
+            Outcome::Passed => ("passed", String::new()),
+            Outcome::Failed(error) => ("failed", format!(",\"error\":{}", json(error))),
+            Outcome::Skipped => ("skipped", String::new()),
+        };
+        // Tracing must not break the pipeline, so write errors are ignored
+        let _ = writeln!(
+            self.out,
+            "{{\"step\":{},\"duration_us\":{},\"outcome\":\"{outcome}\"{error}}}",
+            json(event.step),
+            event.duration.as_micros()
+        );
+    }
+}
+
+fn json(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A `Result` whose steps are recorded in a sink.
+pub struct Traced<'a, T, E> {
+    result: Result<T, E>,
+    sink: &'a mut dyn EventSink,
+}
+
+impl<'a, T, E: fmt::Debug> Traced<'a, T, E> {
+    pub fn new(result: Result<T, E>, sink: &'a mut dyn EventSink) -> Self {
+        Traced { result, sink }
+    }
+
+    /// `bind` that records the step: how long it took and how it ended, or
+    /// that it was skipped after an earlier error.
+    pub fn bind<U, F>(self, step: &'static str, f: F) -> Traced<'a, U, E>
+    where
+        F: FnOnce(T) -> Result<U, E>,
+    {
+        let Traced { result, sink } = self;
+        let (result, duration, outcome) = match result {
+            Ok(value) => {
+                let start = Instant::now();
+                let result = f(value);
+                let outcome = match &result {
+                    Ok(_) => Outcome::Passed,
+                    Err(error) => Outcome::Failed(format!("{error:?}")),
+                };
+                (result, start.elapsed(), outcome)
+            }
+            Err(error) => (Err(error), Duration::ZERO, Outcome::Skipped),
+        };
+        sink.record(StepEvent {
+            step,
+            duration,
+            outcome,
+        });
+        Traced { result, sink }
+    }
+
+    pub fn into_result(self) -> Result<T, E> {
+        self.result
+    }
+}
+// ANCHOR_END: trace
+
+impl<T, E: fmt::Debug> Pipeline<T, E> {
+    /// Runs the pipeline like `run`, recording an event for every step.
+    pub fn run_traced(&self, input: T, sink: &mut dyn EventSink) -> Result<T, StepError<E>> {
+        let mut failed = "";
+        let mut traced = Traced::new(Ok(input), sink);
+        for &(name, ref step) in self.steps() {
+            traced = traced.bind(name, |value| step(value).inspect_err(|_| failed = name));
+        }
+        traced.into_result().map_err(|error| StepError {
+            step: failed,
+            error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn outcomes(sink: &MemorySink) -> Vec<(&str, Outcome)> {
+        sink.events
+            .iter()
+            .map(|event| (event.step, event.outcome.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn records_every_step_of_a_chain() {
+        let mut sink = MemorySink::default();
+        let result = Traced::new(Ok(data("secret", 123)), &mut sink)
+            .bind("validate", validate_data)
+            .bind("encrypt", apply_encryption)
+            .bind("length", |data| Ok(data.content.len()))
+            .into_result();
+        assert_eq!(result.unwrap(), 16);
+        assert_eq!(
+            outcomes(&sink),
+            vec![
+                ("validate", Outcome::Passed),
+                ("encrypt", Outcome::Passed),
+                ("length", Outcome::Passed),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_the_failure_and_the_skipped_steps() {
+        let mut sink = MemorySink::default();
+        let pipeline = Pipeline::new()
+            .step("validate", validate_data)
+            .step("encrypt", apply_encryption)
+            .step("checksum", add_checksum);
+        let error = pipeline.run_traced(data("", 123), &mut sink).unwrap_err();
+        assert_eq!(error.step, "validate");
+        assert_eq!(
+            outcomes(&sink),
+            vec![
+                ("validate", Outcome::Failed("InvalidInput".to_string())),
+                ("encrypt", Outcome::Skipped),
+                ("checksum", Outcome::Skipped),
+            ]
+        );
+        assert!(sink.events[1..]
+            .iter()
+            .all(|event| event.duration.is_zero()));
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let mut sink = JsonLines::new(Vec::new());
+        sink.record(StepEvent {
+            step: "validate",
+            duration: Duration::from_micros(42),
+            outcome: Outcome::Failed("Invalid(\"a\\b\")".to_string()),
+        });
+        sink.record(StepEvent {
+            step: "encrypt",
+            duration: Duration::ZERO,
+            outcome: Outcome::Skipped,
+        });
+        assert_eq!(
+            String::from_utf8(sink.into_inner()).unwrap(),
+            "{\"step\":\"validate\",\"duration_us\":42,\"outcome\":\"failed\",\"error\":\"Invalid(\\\"a\\\\b\\\")\"}\n\
+             {\"step\":\"encrypt\",\"duration_us\":0,\"outcome\":\"skipped\"}\n"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Validation that collects every error instead of stopping at the first.
+
+use crate::{EncryptedData, ProcessError};
+
+// ANCHOR: validated
+/// A list with at least one element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonEmpty<E> {
+    pub first: E,
+    pub rest: Vec<E>,
+}
+
+impl<E> NonEmpty<E> {
+    pub fn new(first: E) -> Self {
+        NonEmpty {
+            first,
+            rest: Vec::new(),
+        }
+    }
+
+    /// `None` for an empty vector.
+    pub fn from_vec(mut errors: Vec<E>) -> Option<Self> {
+        if errors.is_empty() {
+            return None;
+        }
+        let first = errors.remove(0);
+        Some(NonEmpty {
+            first,
+            rest: errors,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        1 + self.rest.len()
+    }
+
+    /// Always `false`: there is at least one element.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        std::iter::once(&self.first).chain(&self.rest)
+    }
+
+    fn append(mut self, other: NonEmpty<E>) -> Self {
+        self.rest.push(other.first);
+        self.rest.extend(other.rest);
+        self
+    }
+}
+
+impl<E> From<NonEmpty<E>> for Vec<E> {
+    fn from(errors: NonEmpty<E>) -> Self {
+        let mut all = vec![errors.first];
+        all.extend(errors.rest);
+        all
+    }
+}
+
+/// The outcome of independent checks: a value, or every error found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Validated<T, E> {
+    Valid(T),
+    Invalid(NonEmpty<E>),
+}
+
+impl<T, E> Validated<T, E> {
+    pub fn invalid(error: E) -> Self {
+        Validated::Invalid(NonEmpty::new(error))
+    }
+
+    /// Combines two checks. Unlike `bind`, both checks have already run, so
+    /// when both failed the errors of both are kept.
+    pub fn zip<U>(self, other: Validated<U, E>) -> Validated<(T, U), E> {
+        match (self, other) {
+            (Validated::Valid(a), Validated::Valid(b)) => Validated::Valid((a, b)),
+            (Validated::Invalid(a), Validated::Invalid(b)) => Validated::Invalid(a.append(b)),
+            (Validated::Invalid(errors), _) | (_, Validated::Invalid(errors)) => {
+                Validated::Invalid(errors)
+            }
+        }
+    }
+
+    /// Like `zip`, but keeps only the value of `self`: `other` is a check
+    /// whose value is not needed.
+    pub fn and_also<U>(self, other: Validated<U, E>) -> Validated<T, E> {
+        self.zip(other).map(|(value, _)| value)
+    }
+
+    pub fn map<U, F>(self, f: F) -> Validated<U, E>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            Validated::Valid(value) => Validated::Valid(f(value)),
+            Validated::Invalid(errors) => Validated::Invalid(errors),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Validated::Valid(_))
+    }
+
+    /// Back to the railway: every error becomes one `Err` with all of them.
+    pub fn into_result(self) -> Result<T, Vec<E>> {
+        Result::from(self).map_err(Vec::from)
+    }
+}
+
+impl<T, E> From<Result<T, E>> for Validated<T, E> {
+    fn from(result: Result<T, E>) -> Self {
+        match result {
+            Ok(value) => Validated::Valid(value),
+            Err(error) => Validated::invalid(error),
+        }
+    }
+}
+
+impl<T, E> From<Validated<T, E>> for Result<T, NonEmpty<E>> {
+    fn from(validated: Validated<T, E>) -> Self {
+        match validated {
+            Validated::Valid(value) => Ok(value),
+            Validated::Invalid(errors) => Err(errors),
+        }
+    }
+}
+// ANCHOR_END: validated
+
+// ANCHOR: validate_fields
+pub fn check_content(content: &str) -> Validated<String, ProcessError> {
+    match content.is_empty() {
+        true => Validated::invalid(ProcessError::InvalidInput),
+        false => Validated::Valid(content.to_string()),
+    }
+}
+
+pub fn check_key(key: i32) -> Validated<i32, ProcessError> {
+    match key > 0 {
+        true => Validated::Valid(key),
+        false => Validated::invalid(ProcessError::ValidationFailed),
+    }
+}
+
+// Both fields are checked even when the first one is already invalid
+pub fn validate_fields(content: &str, key: i32) -> Result<EncryptedData, Vec<ProcessError>> {
+    check_content(content)
+        .zip(check_key(key))
+        .map(|(content, key)| EncryptedData { content, key })
+        .into_result()
+}
+// ANCHOR_END: validate_fields
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MonadicResult;
+
+    #[test]
+    fn zip_keeps_both_values_when_valid() {
+        let data = validate_fields("secret", 7).unwrap();
+        assert_eq!((data.content.as_str(), data.key), ("secret", 7));
+    }
+
+    #[test]
+    fn zip_collects_the_errors_of_every_check() {
+        let errors = validate_fields("", -1).unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [ProcessError::InvalidInput, ProcessError::ValidationFailed]
+        ));
+        let errors = validate_fields("secret", 0).unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [ProcessError::ValidationFailed]
+        ));
+    }
+
+    #[test]
+    fn and_also_keeps_the_first_value() {
+        let both = Validated::<_, &str>::Valid(1).and_also(Validated::Valid("ignored"));
+        assert_eq!(both, Validated::Valid(1));
+        let errors = Validated::<i32, _>::invalid("a")
+            .and_also(Validated::<(), _>::invalid("b"))
+            .and_also(Validated::<(), _>::invalid("c"));
+        assert_eq!(errors.into_result(), Err(vec!["a", "b", "c"]));
+    }
+
+    #[test]
+    fn converts_to_and_from_result() {
+        let valid: Validated<i32, String> = Ok(5).into();
+        assert!(valid.is_valid());
+        let invalid = Validated::from(crate::validate_positive(-5));
+        let result: Result<i32, NonEmpty<String>> = invalid.into();
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors.iter().next().unwrap(), "Number must be positive");
+
+        // Back on the railway, later steps still stop at the error
+        let result = validate_fields("", 0).bind(|data| Ok(data.key));
+        assert_eq!(result.unwrap_err().len(), 2);
+    }
+
+    #[test]
+    fn non_empty_refuses_empty_lists() {
+        assert_eq!(NonEmpty::<i32>::from_vec(Vec::new()), None);
+        let errors = NonEmpty::from_vec(vec![1, 2, 3]).unwrap();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(Vec::from(errors), vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Checks the compile errors of misused macros word for word.
+//!
+//! Every `tests/ui/<group>/<case>.rs` is compiled with `rustc` (`$RUSTC` when
+//! set) against this crate, and must fail with the stderr in `<case>.stderr`
+//! next to it. Run with `UPDATE_UI=1` to write the `.stderr` files from the
+//! current output instead, then review the diff.
+
+use std::env;
+use std::fs;
+use std::hint;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use traits_functional::{validate_data, EncryptedData, ProcessResult};
+
+fn crate_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Directory holding this test and the libraries it was built with.
+fn deps() -> PathBuf {
+    let test = env::current_exe().unwrap();
+    test.parent().unwrap().to_path_buf()
+}
+
+/// The build of this crate's library in `deps` that this test is linked
+/// with. Cargo leaves older builds next to it, and symbol mangling puts a
+/// hash of the build in every name, so the names this test has for
+/// `validate_data` are looked up in each of them.
+fn library(deps: &Path) -> &'static Path {
+    static LIBRARY: OnceLock<PathBuf> = OnceLock::new();
+    LIBRARY.get_or_init(|| linked_library(deps))
+}
+
+fn linked_library(deps: &Path) -> PathBuf {
+    let linked: fn(EncryptedData) -> ProcessResult = validate_data;
+    hint::black_box(linked);
+    let test = fs::read(env::current_exe().unwrap()).unwrap();
+    let symbols = symbols(&test, b"traits_functional13validate_data");
+    assert!(
+        !symbols.is_empty(),
+        "no `validate_data` symbol in this test"
+    );
+    fs::read_dir(deps)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("libtraits_functional-") && name.ends_with(".rlib")
+        })
+        .find(|path| {
+            let library = fs::read(path).unwrap();
+            symbols
+                .iter()
+                .any(|symbol| find(&library, symbol).is_some())
+        })
+        .unwrap_or_else(|| {
+            panic!(
+                "no libtraits_functional rlib in {} matches this test",
+                deps.display()
+            )
+        })
+}
+
+/// The distinct mangled names in `bytes` that contain `part`.
+fn symbols<'a>(bytes: &'a [u8], part: &[u8]) -> Vec<&'a [u8]> {
+    let in_symbol = |byte: &u8| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'$');
+    let mut symbols = Vec::new();
+    let mut rest = 0;
+    while let Some(offset) = find(&bytes[rest..], part) {
+        let at = rest + offset;
+        let start = bytes[..at]
+            .iter()
+            .rposition(|byte| !in_symbol(byte))
+            .map_or(0, |i| i + 1);
+        let end = bytes[at..]
+            .iter()
+            .position(|byte| !in_symbol(byte))
+            .map_or(bytes.len(), |i| at + i);
+        if !symbols.contains(&&bytes[start..end]) {
+            symbols.push(&bytes[start..end]);
+        }
+        rest = end;
+    }
+    symbols
+}
+
+fn find(bytes: &[u8], part: &[u8]) -> Option<usize> {
+    let first = *part.first()?;
+    (0..bytes.len().saturating_sub(part.len() - 1))
+        .find(|&i| bytes[i] == first && bytes[i..].starts_with(part))
+}
+
+/// The stderr of compiling `case`, which must fail. Paths that differ
+/// between machines become `$CRATE`, and locations outside the case become
+/// `LL:CC`, so that editing the library does not change the expected output.
+fn compile(case: &Path, scratch: &Path) -> String {
+    let deps = deps();
+    let rustc = env::var_os("RUSTC").map_or_else(|| "rustc".into(), PathBuf::from);
+    let output = Command::new(rustc)
+        .current_dir(case.parent().unwrap())
+        .args(["--edition", "2021", "--emit", "metadata", "-A", "warnings"])
+        .arg("--out-dir")
+        .arg(scratch)
+        .arg("-L")
+        .arg(format!("dependency={}", deps.display()))
+        .arg("--extern")
+        .arg(format!("traits_functional={}", library(&deps).display()))
+        .arg(case.file_name().unwrap())
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .unwrap();
+    assert!(
+        !output.status.success(),
+        "{} compiled, but it is expected to fail",
+        case.display()
+    );
+    let crate_dir = crate_dir().canonicalize().unwrap();
+    let stderr =
+        String::from_utf8_lossy(&output.stderr).replace(crate_dir.to_str().unwrap(), "$CRATE");
+    let case_name = case.file_name().unwrap().to_str().unwrap();
+    stderr
+        .lines()
+        .map(|line| match line.trim_start().strip_prefix("--> ") {
+            Some(location) if !location.starts_with(case_name) => {
+                let path = location.split(':').next().unwrap_or(location);
+                format!("{}{path}:LL:CC\n", &line[..line.len() - location.len()])
+            }
+            _ => format!("{line}\n"),
+        })
+        .collect()
+}
+
+/// Compiles every case of `group` and compares its errors.
+fn check(group: &str) {
+    let dir = crate_dir().join("tests").join("ui").join(group);
+    let scratch = env::temp_dir().join(format!(
+        "traits-functional-ui-{}-{group}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&scratch).unwrap();
+    let mut cases: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "rs"))
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no cases in {}", dir.display());
+
+    let mut failures = String::new();
+    for case in cases {
+        let actual = compile(&case, &scratch);
+        let expected_file = case.with_extension("stderr");
+        if env::var_os("UPDATE_UI").is_some() {
+            fs::write(&expected_file, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_file).unwrap_or_default();
+        if actual != expected {
+            failures.push_str(&format!(
+                "{}: stderr differs from {}\n--- expected\n{expected}\n--- actual\n{actual}\n",
+                case.display(),
+                expected_file.display()
+            ));
+        }
+    }
+    fs::remove_dir_all(&scratch).unwrap();
+    assert!(failures.is_empty(), "{failures}");
+}
+
+#[test]
+fn railway_misuse() {
+    check("railway");
+}
+
+#[test]
+fn pipeline_error_misuse() {
+    check("pipeline_error");
+}
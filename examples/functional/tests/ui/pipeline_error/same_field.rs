@@ -0,0 +1,10 @@
+use traits_functional::convert::PipelineError;
+
+#[derive(Debug, PipelineError)]
+enum LoadError {
+    Parse(String),
+    /// Reading failed
+    Read(String),
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use traits_functional::convert::PipelineError;
+
+#[derive(Debug, PipelineError)]
+struct LoadError(String);
+
+fn main() {}
@@ -0,0 +1,8 @@
+use traits_functional::convert::PipelineError;
+
+#[derive(Debug, PipelineError)]
+enum LoadError {
+    Located(String, usize),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use traits_functional::convert::PipelineError;
+
+#[derive(Debug, PipelineError)]
+enum LoadError {
+    Number(std::num::ParseIntError),
+    Unknown,
+}
+
+fn main() {}
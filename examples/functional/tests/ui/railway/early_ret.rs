@@ -0,0 +1,8 @@
+use traits_functional::railway;
+
+fn main() {
+    let _: Result<i32, String> = railway! {
+        ret 1;
+        let a <- Ok(2);
+    };
+}
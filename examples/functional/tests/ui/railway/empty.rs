@@ -0,0 +1,5 @@
+use traits_functional::railway;
+
+fn main() {
+    let _: Result<i32, String> = railway! {};
+}
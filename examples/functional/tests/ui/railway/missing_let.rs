@@ -0,0 +1,8 @@
+use traits_functional::railway;
+
+fn main() {
+    let _: Result<i32, String> = railway! {
+        a <- Ok(1);
+        ret a
+    };
+}
@@ -0,0 +1,9 @@
+use traits_functional::railway;
+
+fn main() {
+    let _: Result<i32, String> = railway! {
+        let a <- Ok(1);
+        ;
+        ret a
+    };
+}
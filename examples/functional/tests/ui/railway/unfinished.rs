@@ -0,0 +1,7 @@
+use traits_functional::railway;
+
+fn main() {
+    let _: Result<i32, String> = railway! {
+        let a <- Ok(1);
+    };
+}
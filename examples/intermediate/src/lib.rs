@@ -0,0 +1,160 @@
+//! Examples from `traits-intermediate.md`.
+//!
+//! The regions between `// ANCHOR:` comments are included in the chapter.
+
+// ANCHOR: derive
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SomeStruct {}
+// ANCHOR_END: derive
+
+// ANCHOR: some_enum
+pub enum SomeEnum {
+    SomeField1,
+    SomeField2,
+}
+// ANCHOR_END: some_enum
+
+// The chapter shows the manual implementation that `#[derive(Default)]` would generate.
+#[allow(clippy::derivable_impls)]
+// ANCHOR: default
+impl Default for SomeEnum {
+    fn default() -> Self {
+        SomeEnum::SomeField1
+    }
+}
+// ANCHOR_END: default
+
+// ANCHOR: from
+impl From<u8> for SomeEnum {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => SomeEnum::SomeField1,
+            2 => SomeEnum::SomeField2,
+            _ => panic!("value is not valid"),
+        }
+    }
+}
+// ANCHOR_END: from
+
+// ANCHOR: from_str
+use std::str::FromStr;
+
+impl FromStr for SomeEnum {
+    type Err = String;
+
+    fn from_str(some_string: &str) -> Result<Self, Self::Err> {
+        match some_string {
+            "SomeField1" => Ok(SomeEnum::SomeField1),
+            "SomeField2" => Ok(SomeEnum::SomeField2),
+            _ => Err("Invalid string".to_string()),
+        }
+    }
+}
+// ANCHOR_END: from_str
+
+// ANCHOR: display
+use std::fmt;
+
+impl fmt::Display for SomeEnum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SomeEnum::SomeField1 => write!(f, "Field 1"),
+            SomeEnum::SomeField2 => write!(f, "Field 2"),
+        }
+    }
+}
+// ANCHOR_END: display
+
+// ANCHOR: add
+use std::ops::Add;
+
+#[derive(Debug)]
+pub struct Counter {
+    pub count: i32,
+}
+
+impl Add for Counter {
+    type Output = Counter;
+
+    fn add(self, other: Self) -> Self::Output {
+        Counter {
+            count: self.count + other.count,
+        }
+    }
+}
+// ANCHOR_END: add
+
+// ANCHOR: sub
+use std::ops::Sub;
+
+impl Sub for Counter {
+    type Output = Counter;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Counter {
+            count: self.count - other.count,
+        }
+    }
+}
+// ANCHOR_END: sub
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derived_traits() {
+        let a = SomeStruct {};
+        let b = a;
+        assert_eq!(a, b.clone());
+        assert_eq!(format!("{a:?}"), "SomeStruct");
+    }
+
+    #[test]
+    fn default_is_the_first_field() {
+        assert!(matches!(SomeEnum::default(), SomeEnum::SomeField1));
+    }
+
+    #[test]
+    fn from_and_into_u8() {
+        assert!(matches!(SomeEnum::from(1), SomeEnum::SomeField1));
+        let value: SomeEnum = 2.into();
+        assert!(matches!(value, SomeEnum::SomeField2));
+    }
+
+    #[test]
+    #[should_panic(expected = "value is not valid")]
+    fn from_panics_on_unknown_values() {
+        let _ = SomeEnum::from(3);
+    }
+
+    #[test]
+    fn parses_from_strings() {
+        assert!(matches!(
+            "SomeField2".parse::<SomeEnum>(),
+            Ok(SomeEnum::SomeField2)
+        ));
+        assert!(matches!(
+            SomeEnum::from_str("SomeField1"),
+            Ok(SomeEnum::SomeField1)
+        ));
+        assert_eq!(
+            "Other".parse::<SomeEnum>().err(),
+            Some("Invalid string".to_string())
+        );
+    }
+
+    #[test]
+    fn displays_readable_names() {
+        assert_eq!(SomeEnum::SomeField1.to_string(), "Field 1");
+        assert_eq!(format!("Value: {}", SomeEnum::SomeField2), "Value: Field 2");
+    }
+
+    #[test]
+    fn counters_add_and_subtract() {
+        let sum = Counter { count: 5 } + Counter { count: 3 };
+        assert_eq!(sum.count, 8);
+        let difference = Counter { count: 5 } - Counter { count: 3 };
+        assert_eq!(difference.count, 2);
+    }
+}
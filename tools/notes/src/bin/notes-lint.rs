@@ -0,0 +1,39 @@
+//! Checks the structure and spelling of every chapter.
+//!
+//! Usage: `notes-lint [ROOT]`
+//!
+//! Problems are reported by file, line and rule. Unknown words can be added
+//! to the `cSpell.words` list of `ROOT/.vscode/settings.json`.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use notes::lint;
+
+fn main() -> ExitCode {
+    let mut args = env::args_os().skip(1);
+    let root = args
+        .next()
+        .map_or_else(|| PathBuf::from("."), PathBuf::from);
+    if args.next().is_some() || root.to_str().is_some_and(|arg| arg.starts_with("--")) {
+        eprintln!("usage: notes-lint [ROOT]");
+        return ExitCode::FAILURE;
+    }
+    match lint::lint(&root) {
+        Ok(lints) => {
+            for lint in &lints {
+                println!("{lint}");
+            }
+            println!("{} problems", lints.len());
+            match lints.is_empty() {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::FAILURE,
+            }
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
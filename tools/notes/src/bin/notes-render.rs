@@ -0,0 +1,100 @@
+//! Expands the `{{#include path:anchor}}` directives of every chapter into
+//! rendered Markdown, failing when a file or anchor is missing.
+//!
+//! Usage: `notes-render [--check] [--out DIR] [ROOT]`
+//!
+//! The rendered chapters are written to `DIR` (default `ROOT/target/notes`)
+//! with the same relative paths as their sources. With `--check`, nothing is
+//! written and only broken directives are reported.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use notes::{include, snippet};
+
+struct Args {
+    check: bool,
+    out: Option<PathBuf>,
+    root: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        check: false,
+        out: None,
+        root: PathBuf::from("."),
+    };
+    let mut input = env::args_os().skip(1);
+    while let Some(arg) = input.next() {
+        match arg.to_str() {
+            Some("--check") => args.check = true,
+            Some("--out") => {
+                args.out = Some(input.next().ok_or("--out needs a directory")?.into());
+            }
+            Some(flag) if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+            _ => args.root = PathBuf::from(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}");
+            eprintln!("usage: notes-render [--check] [--out DIR] [ROOT]");
+            return ExitCode::FAILURE;
+        }
+    };
+    match render(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Renders every chapter, returning whether all directives resolved.
+fn render(args: &Args) -> std::io::Result<bool> {
+    let out = args
+        .out
+        .clone()
+        .unwrap_or_else(|| args.root.join("target").join("notes"));
+    let chapters = snippet::chapters(&args.root)?;
+    let mut broken = 0;
+    for chapter in &chapters {
+        let source = fs::read_to_string(chapter)?;
+        match include::expand(chapter, &source) {
+            Ok(expanded) if !args.check => {
+                let relative = chapter.strip_prefix(&args.root).unwrap_or(chapter);
+                let target = out.join(relative);
+                fs::create_dir_all(target.parent().unwrap_or(Path::new(".")))?;
+                fs::write(&target, expanded.text)?;
+            }
+            Ok(_) => {}
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{error}");
+                }
+                broken += errors.len();
+            }
+        }
+    }
+    match args.check {
+        true => println!(
+            "checked {} chapters: {broken} broken include directives",
+            chapters.len()
+        ),
+        false => println!(
+            "rendered {} chapters to {}: {broken} broken include directives",
+            chapters.len(),
+            out.display()
+        ),
+    }
+    Ok(broken == 0)
+}
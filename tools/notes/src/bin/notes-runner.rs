@@ -0,0 +1,88 @@
+//! Serves the notes site locally and runs snippets for its "Run" buttons.
+//!
+//! Usage: `notes-runner [--port PORT] [--site DIR]`
+//!
+//! Listens on `127.0.0.1:PORT` (default 3030) only. The pages under `DIR`
+//! (default `target/site`, as written by `notes-site`) are served at the
+//! root. Only those pages may run snippets: the runner refuses pages opened
+//! straight from disk, as it cannot tell them from other web sites.
+
+use std::env;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use notes::runner::Limits;
+use notes::server::{Server, DEFAULT_PORT};
+
+struct Args {
+    port: u16,
+    site: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        port: DEFAULT_PORT,
+        site: PathBuf::from("target").join("site"),
+    };
+    let mut input = env::args_os().skip(1);
+    while let Some(arg) = input.next() {
+        match arg.to_str() {
+            Some("--port") => {
+                let port = input.next().ok_or("--port needs a number")?;
+                args.port = port
+                    .to_str()
+                    .and_then(|port| port.parse().ok())
+                    .ok_or("--port needs a number")?;
+            }
+            Some("--site") => {
+                args.site = input.next().ok_or("--site needs a directory")?.into();
+            }
+            _ => return Err(format!("unexpected argument {}", arg.to_string_lossy())),
+        }
+    }
+    Ok(args)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}");
+            eprintln!("usage: notes-runner [--port PORT] [--site DIR]");
+            return ExitCode::FAILURE;
+        }
+    };
+    let listener = match TcpListener::bind(("127.0.0.1", args.port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("error: cannot listen on 127.0.0.1:{}: {err}", args.port);
+            return ExitCode::FAILURE;
+        }
+    };
+    let site = match args.site.is_dir() {
+        true => {
+            println!(
+                "serving {} at http://127.0.0.1:{}/",
+                args.site.display(),
+                args.port
+            );
+            Some(args.site)
+        }
+        false => {
+            println!(
+                "no site at {}; run `notes-site` to build it. Running snippets on port {}",
+                args.site.display(),
+                args.port
+            );
+            None
+        }
+    };
+    match Server::new(site, args.port, Limits::default()).serve(listener) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Searches the prose and code of the notes.
+//!
+//! Usage: `notes-search [--root DIR] [--limit N] [--json] (--symbol NAME | WORDS...)`
+//!        `notes-search [--root DIR] --index FILE`
+//!
+//! `--symbol` finds the sections that define, implement or use a Rust
+//! identifier; otherwise the sections containing every word are listed. Hits
+//! are ranked best first, each with the chapter line and a preview. With
+//! `--index`, the whole index is written to `FILE` as JSON instead.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use notes::search::Index;
+
+struct Args {
+    root: PathBuf,
+    limit: usize,
+    json: bool,
+    symbol: Option<String>,
+    index: Option<PathBuf>,
+    words: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        root: PathBuf::from("."),
+        limit: 10,
+        json: false,
+        symbol: None,
+        index: None,
+        words: Vec::new(),
+    };
+    let mut input = env::args().skip(1);
+    while let Some(arg) = input.next() {
+        match arg.as_str() {
+            "--root" => args.root = input.next().ok_or("--root needs a directory")?.into(),
+            "--limit" => {
+                args.limit = input
+                    .next()
+                    .and_then(|limit| limit.parse().ok())
+                    .ok_or("--limit needs a number")?;
+            }
+            "--json" => args.json = true,
+            "--symbol" => args.symbol = Some(input.next().ok_or("--symbol needs a name")?),
+            "--index" => args.index = Some(input.next().ok_or("--index needs a file")?.into()),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+            _ => args.words.push(arg),
+        }
+    }
+    match (&args.symbol, &args.index, args.words.is_empty()) {
+        (None, None, true) => Err("nothing to search for".to_string()),
+        (Some(_), _, false) => Err("give either --symbol or search words".to_string()),
+        _ => Ok(args),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}");
+            eprintln!(
+                "usage: notes-search [--root DIR] [--limit N] [--json] (--symbol NAME | WORDS...)"
+            );
+            eprintln!("       notes-search [--root DIR] --index FILE");
+            return ExitCode::FAILURE;
+        }
+    };
+    let index = match Index::build(&args.root) {
+        Ok(index) => index,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(file) = &args.index {
+        return match fs::write(file, index.to_json()) {
+            Ok(()) => {
+                println!(
+                    "indexed {} sections into {}",
+                    index.sections.len(),
+                    file.display()
+                );
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("error: cannot write {}: {err}", file.display());
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let mut hits = match &args.symbol {
+        Some(symbol) => index.symbol(symbol),
+        None => index.text(&args.words.join(" ")),
+    };
+    hits.truncate(args.limit);
+    if args.json {
+        let hits: Vec<String> = hits.iter().map(|hit| hit.to_json()).collect();
+        println!("[{}]", hits.join(",\n"));
+    } else {
+        for hit in &hits {
+            println!(
+                "{}:{}: {} [score {}]",
+                hit.section.chapter, hit.line, hit.section.heading, hit.score
+            );
+            println!("    {}", hit.preview);
+        }
+    }
+    match hits.is_empty() {
+        true => {
+            eprintln!("no matches");
+            ExitCode::FAILURE
+        }
+        false => ExitCode::SUCCESS,
+    }
+}
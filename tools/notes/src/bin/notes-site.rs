@@ -0,0 +1,65 @@
+//! Builds a static HTML site of the notes.
+//!
+//! Usage: `notes-site [--out DIR] [ROOT]`
+//!
+//! The site is written to `DIR` (default `ROOT/target/site`). It has no
+//! external resources; open `index.html` in a browser. The search index is
+//! written next to the pages as `search-index.json`.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use notes::search::Index;
+use notes::site;
+
+struct Args {
+    out: Option<PathBuf>,
+    root: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        out: None,
+        root: PathBuf::from("."),
+    };
+    let mut input = env::args_os().skip(1);
+    while let Some(arg) = input.next() {
+        match arg.to_str() {
+            Some("--out") => {
+                args.out = Some(input.next().ok_or("--out needs a directory")?.into());
+            }
+            Some(flag) if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+            _ => args.root = PathBuf::from(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}");
+            eprintln!("usage: notes-site [--out DIR] [ROOT]");
+            return ExitCode::FAILURE;
+        }
+    };
+    let out = args
+        .out
+        .unwrap_or_else(|| args.root.join("target").join("site"));
+    let result = site::build(&args.root).and_then(|pages| {
+        site::write(&pages, &Index::build(&args.root)?, &out)?;
+        Ok(pages.len())
+    });
+    match result {
+        Ok(count) => {
+            println!("wrote {count} pages to {}", out.display());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Links the chapters of the notes to each other.
+//!
+//! Usage: `notes-xref [--check] [ROOT]`
+//!
+//! Traits and types used in more than one chapter get a "See also" block at
+//! the end of each chapter using them and an entry in `glossary.md`. Every
+//! relative link in the notes is then checked, and the tool fails when one
+//! names a missing file or heading. With `--check`, nothing is written and
+//! out-of-date generated files are reported as well.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use notes::xref;
+
+struct Args {
+    check: bool,
+    root: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        check: false,
+        root: PathBuf::from("."),
+    };
+    for arg in env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("--check") => args.check = true,
+            Some(flag) if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+            _ => args.root = PathBuf::from(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}");
+            eprintln!("usage: notes-xref [--check] [ROOT]");
+            return ExitCode::FAILURE;
+        }
+    };
+    match link(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Updates or checks the generated files, returning whether the notes are
+/// up to date and free of broken links.
+fn link(args: &Args) -> std::io::Result<bool> {
+    let references = xref::analyze(&args.root)?;
+    let mut stale = 0;
+    for (path, text) in references.generated(&args.root)? {
+        if fs::read_to_string(&path).ok().as_deref() == Some(text.as_str()) {
+            continue;
+        }
+        stale += 1;
+        match args.check {
+            true => eprintln!("{}: out of date, run notes-xref", path.display()),
+            false => fs::write(&path, text)?,
+        }
+    }
+    let broken = xref::broken_links(&args.root)?;
+    for link in &broken {
+        eprintln!("{link}");
+    }
+    let verb = if args.check { "out of date" } else { "updated" };
+    println!(
+        "{} terms across {} chapters: {stale} files {verb}, {} broken links",
+        references.terms.len(),
+        references.chapters.len(),
+        broken.len()
+    );
+    Ok(broken.is_empty() && (stale == 0 || !args.check))
+}
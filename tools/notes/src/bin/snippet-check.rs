@@ -0,0 +1,67 @@
+//! Compiles every ```rust block in the notes and reports failures by file and line.
+//!
+//! Fence directives (`compile_fail`, `should_panic`, `no_run`, `ignore`,
+//! `standalone`) declare what each snippet is expected to do.
+//!
+//! Usage: `snippet-check [PATH...]`, where each path is a chapter file or a
+//! directory to search for chapters (defaults to the current directory).
+
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use notes::check;
+use notes::snippet;
+
+fn main() -> ExitCode {
+    let mut paths: Vec<PathBuf> = env::args_os().skip(1).map(PathBuf::from).collect();
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let mut chapters = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            match snippet::chapters(&path) {
+                Ok(found) => chapters.extend(found),
+                Err(err) => {
+                    eprintln!("error: cannot read {}: {err}", path.display());
+                    return ExitCode::FAILURE;
+                }
+            }
+        } else {
+            chapters.push(path);
+        }
+    }
+
+    let (mut checked, mut ignored, mut failed) = (0, 0, 0);
+    for chapter in &chapters {
+        let outcomes = match check::check_chapter(chapter) {
+            Ok(outcomes) => outcomes,
+            Err(err) => {
+                eprintln!("error: cannot check {}: {err}", chapter.display());
+                return ExitCode::FAILURE;
+            }
+        };
+        for outcome in outcomes {
+            checked += 1;
+            if outcome.ignored() {
+                ignored += 1;
+            } else if !outcome.passed() {
+                failed += 1;
+                eprint!("{outcome}");
+            }
+        }
+    }
+
+    println!(
+        "checked {checked} snippets in {} chapters: {} passed, {ignored} ignored, {failed} failed",
+        chapters.len(),
+        checked - ignored - failed
+    );
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,662 @@
+//! Compiles snippets with `rustc` and maps diagnostics back to the notes.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::harness::{self, Program};
+use crate::snippet::{Expect, Output, Snippet};
+
+/// A compiler message attributed to a chapter line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Chapter line the message points at, or `None` for harness code.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// How a snippet fared against the expectation declared by its directives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Passed,
+    Ignored,
+    Failed(Failure),
+}
+
+/// Why a snippet did not meet its expectation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The fence info string could not be parsed.
+    Directive(String),
+    /// The snippet was expected to compile but did not.
+    Compile(Vec<Diagnostic>),
+    /// A `compile_fail` snippet compiled.
+    Compiled,
+    /// A `compile_fail` snippet failed without the expected error codes.
+    MissingCodes {
+        codes: Vec<String>,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// The program exited unsuccessfully or panicked.
+    Run { status: String, stderr: String },
+    /// The program printed something other than the documented output.
+    Output {
+        /// Line of the expected output block.
+        line: usize,
+        diff: String,
+    },
+    /// A `should_panic` snippet ran to completion.
+    NoPanic,
+    /// The program did not finish within [`RUN_TIMEOUT`].
+    TimedOut,
+}
+
+/// The result of checking one snippet.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub snippet: Snippet,
+    pub verdict: Verdict,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.verdict == Verdict::Passed
+    }
+
+    pub fn ignored(&self) -> bool {
+        self.verdict == Verdict::Ignored
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.snippet.path.display();
+        let location = format!("{path}:{}", self.snippet.line);
+        let diagnostics = |f: &mut fmt::Formatter<'_>, diagnostics: &[Diagnostic]| {
+            for diagnostic in diagnostics {
+                match diagnostic.line {
+                    Some(line) => writeln!(f, "  {path}:{line}: {}", diagnostic.message)?,
+                    None => writeln!(f, "  (harness): {}", diagnostic.message)?,
+                }
+            }
+            Ok(())
+        };
+        match &self.verdict {
+            Verdict::Passed => writeln!(f, "{location}: ok"),
+            Verdict::Ignored => writeln!(f, "{location}: ignored"),
+            Verdict::Failed(Failure::Directive(message)) => writeln!(f, "{location}: {message}"),
+            Verdict::Failed(Failure::Compile(found)) => {
+                writeln!(f, "{location}: snippet failed to compile")?;
+                diagnostics(f, found)
+            }
+            Verdict::Failed(Failure::Compiled) => {
+                writeln!(f, "{location}: snippet compiled but is marked compile_fail")
+            }
+            Verdict::Failed(Failure::MissingCodes {
+                codes,
+                diagnostics: found,
+            }) => {
+                writeln!(
+                    f,
+                    "{location}: snippet failed to compile, but not with {}",
+                    codes.join(", ")
+                )?;
+                diagnostics(f, found)
+            }
+            Verdict::Failed(Failure::Run { status, stderr }) => {
+                writeln!(f, "{location}: snippet {status}")?;
+                for line in stderr.lines() {
+                    writeln!(f, "  {line}")?;
+                }
+                Ok(())
+            }
+            Verdict::Failed(Failure::Output { line, diff }) => {
+                writeln!(
+                    f,
+                    "{location}: output differs from {path}:{line} (-expected +actual)"
+                )?;
+                for line in diff.lines() {
+                    writeln!(f, "  {line}")?;
+                }
+                Ok(())
+            }
+            Verdict::Failed(Failure::NoPanic) => {
+                writeln!(
+                    f,
+                    "{location}: snippet ran without panicking but is marked should_panic"
+                )
+            }
+            Verdict::Failed(Failure::TimedOut) => writeln!(
+                f,
+                "{location}: snippet did not finish within {} seconds",
+                RUN_TIMEOUT.as_secs()
+            ),
+        }
+    }
+}
+
+/// How long a compiled snippet may run before it is killed.
+pub const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long `rustc` may take to build a snippet before it is killed.
+pub const BUILD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Exit code of a Rust program that panicked on the main thread.
+const PANIC_EXIT_CODE: i32 = 101;
+
+/// What a compiled snippet did when run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    /// Exit code, or `None` if the process was killed by a signal or timed out.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,
+}
+
+/// Invokes `rustc` in a scratch directory that is removed on drop.
+#[derive(Debug)]
+pub struct Compiler {
+    rustc: PathBuf,
+    dir: PathBuf,
+}
+
+impl Compiler {
+    /// Uses `$RUSTC` when set, otherwise `rustc` from `PATH`.
+    pub fn new() -> io::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "notes-snippets-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        Ok(Compiler {
+            rustc: env::var_os("RUSTC").map_or_else(|| "rustc".into(), PathBuf::from),
+            dir,
+        })
+    }
+
+    /// Builds `program` into an executable named after `name`, killing
+    /// `rustc` after [`BUILD_TIMEOUT`].
+    pub fn build(&self, name: &str, program: &Program) -> io::Result<Vec<Diagnostic>> {
+        self.build_within(name, program, BUILD_TIMEOUT)
+    }
+
+    /// Builds `program` like [`Compiler::build`], killing `rustc` and the
+    /// linker it started after `timeout`.
+    pub fn build_within(
+        &self,
+        name: &str,
+        program: &Program,
+        timeout: Duration,
+    ) -> io::Result<Vec<Diagnostic>> {
+        let source = self.dir.join(format!("{name}.rs"));
+        fs::write(&source, &program.source)?;
+        let mut child = spawn_in_group(
+            Command::new(&self.rustc)
+                .current_dir(&self.dir)
+                .args(["--edition", "2021", "--crate-name", "snippet"])
+                .args(["-A", "warnings", "--error-format", "short", "-o"])
+                .arg(name)
+                .arg(format!("{name}.rs"))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped()),
+        )?;
+        let stderr = read_in_background(child.stderr.take());
+        let status = wait_within(&mut child, timeout)?;
+        let stderr = stderr.join().unwrap_or_default();
+        match status {
+            Some(status) if status.success() => return Ok(Vec::new()),
+            Some(_) => {}
+            None => {
+                return Ok(vec![Diagnostic {
+                    line: None,
+                    message: format!("rustc did not finish within {timeout:?}"),
+                }])
+            }
+        }
+        let mut diagnostics = parse_diagnostics(&stderr, &format!("{name}.rs"), program);
+        if diagnostics.is_empty() {
+            diagnostics.push(Diagnostic {
+                line: None,
+                message: stderr.trim().to_string(),
+            });
+        }
+        Ok(diagnostics)
+    }
+
+    /// The scratch directory programs are built and run in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the executable [`Compiler::build`] produces for `name`.
+    pub fn executable(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Runs the executable produced by [`Compiler::build`], killing it after `timeout`.
+    pub fn run(&self, name: &str, timeout: Duration) -> io::Result<Run> {
+        let mut child = spawn_in_group(
+            Command::new(self.executable(name))
+                .current_dir(&self.dir)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )?;
+        let stdout = read_in_background(child.stdout.take());
+        let stderr = read_in_background(child.stderr.take());
+        let status = wait_within(&mut child, timeout)?;
+        Ok(Run {
+            code: status.and_then(|status| status.code()),
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+            timed_out: status.is_none(),
+        })
+    }
+}
+
+/// Starts `command` in a process group of its own, so that [`kill_group`]
+/// also reaches the processes it starts: the linker `rustc` runs, or the
+/// children a program forks.
+pub(crate) fn spawn_in_group(command: &mut Command) -> io::Result<Child> {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    command.spawn()
+}
+
+/// Kills a child started with [`spawn_in_group`] and, on Unix, every other
+/// process left in its group.
+pub(crate) fn kill_group(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    Command::new("/bin/sh")
+        .arg("-c")
+        .arg("kill -KILL -$0")
+        .arg(child.id().to_string())
+        .stderr(Stdio::null())
+        .status()?;
+    child.kill()
+}
+
+/// Waits for `child` until `timeout` has passed, then kills its group;
+/// `None` means it was killed.
+fn wait_within(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            kill_group(child)?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+impl Drop for Compiler {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Parses `--error-format short` output such as
+/// `snippet_3.rs:12:5: error[E0063]: missing field`.
+fn parse_diagnostics(stderr: &str, file: &str, program: &Program) -> Vec<Diagnostic> {
+    stderr
+        .lines()
+        .filter_map(|line| {
+            if let Some(rest) = line.strip_prefix(file).and_then(|r| r.strip_prefix(':')) {
+                let mut parts = rest.splitn(3, ':');
+                let number = parts.next()?.parse().ok()?;
+                let _column = parts.next()?;
+                let message = parts.next()?.trim().to_string();
+                Some(Diagnostic {
+                    line: program.origin(number),
+                    message,
+                })
+            } else if line.starts_with("error") && !line.contains("aborting due to") {
+                Some(Diagnostic {
+                    line: None,
+                    message: line.to_string(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Checks every snippet of one chapter against its directives, in parallel.
+pub fn check_chapter(path: &Path) -> io::Result<Vec<Outcome>> {
+    let snippets = crate::snippet::load(path)?;
+    let programs = harness::assemble_chapter(&snippets);
+    let compiler = Compiler::new()?;
+    let verdicts = parallel(snippets.len(), |index| {
+        let (directives, program) = match &programs[index] {
+            Ok(assembled) => assembled,
+            Err(message) => return Ok(Verdict::Failed(Failure::Directive(message.clone()))),
+        };
+        judge(
+            &compiler,
+            &format!("snippet_{index}"),
+            program,
+            &directives.expect,
+            snippets[index].output.as_ref(),
+        )
+    });
+    snippets
+        .into_iter()
+        .zip(verdicts)
+        .map(|(snippet, verdict)| {
+            Ok(Outcome {
+                snippet,
+                verdict: verdict?,
+            })
+        })
+        .collect()
+}
+
+/// Builds and, depending on `expect`, runs a program, comparing its stdout
+/// with `output` when the snippet documents one.
+fn judge(
+    compiler: &Compiler,
+    name: &str,
+    program: &Program,
+    expect: &Expect,
+    output: Option<&Output>,
+) -> io::Result<Verdict> {
+    if output.is_some() && !matches!(expect, Expect::Run | Expect::ShouldPanic) {
+        return Ok(Verdict::Failed(Failure::Directive(
+            "expected output is only checked for snippets that run".to_string(),
+        )));
+    }
+    if *expect == Expect::Ignore {
+        return Ok(Verdict::Ignored);
+    }
+    let diagnostics = compiler.build(name, program)?;
+    let failure = match expect {
+        Expect::Ignore => unreachable!("handled above"),
+        Expect::CompileFail(_) if diagnostics.is_empty() => Some(Failure::Compiled),
+        Expect::CompileFail(codes) => {
+            let found = |code: &String| {
+                diagnostics
+                    .iter()
+                    .any(|diagnostic| diagnostic.message.contains(&format!("[{code}]")))
+            };
+            match codes.iter().all(found) {
+                true => None,
+                false => Some(Failure::MissingCodes {
+                    codes: codes.clone(),
+                    diagnostics,
+                }),
+            }
+        }
+        _ if !diagnostics.is_empty() => Some(Failure::Compile(diagnostics)),
+        Expect::NoRun => None,
+        Expect::Run | Expect::ShouldPanic => {
+            let run = compiler.run(name, RUN_TIMEOUT)?;
+            let panicked = run.code == Some(PANIC_EXIT_CODE);
+            let unexpected = output.and_then(|expected| {
+                let diff = diff(&expected.text, &run.stdout)?;
+                Some(Failure::Output {
+                    line: expected.line,
+                    diff,
+                })
+            });
+            match (expect, run.code) {
+                _ if run.timed_out => Some(Failure::TimedOut),
+                (Expect::Run, Some(0)) => unexpected,
+                (Expect::ShouldPanic, _) if panicked => unexpected,
+                (Expect::ShouldPanic, Some(0)) => Some(Failure::NoPanic),
+                (_, code) => Some(Failure::Run {
+                    status: match code {
+                        Some(PANIC_EXIT_CODE) => "panicked".to_string(),
+                        Some(code) => format!("exited with status {code}"),
+                        None => "was killed by a signal".to_string(),
+                    },
+                    stderr: run.stderr,
+                }),
+            }
+        }
+    };
+    Ok(failure.map_or(Verdict::Passed, Verdict::Failed))
+}
+
+/// A line diff of expected and actual output, or `None` if they match.
+///
+/// Trailing whitespace is ignored. Lines only in `expected` are prefixed with
+/// `-`, lines only in `actual` with `+`.
+fn diff(expected: &str, actual: &str) -> Option<String> {
+    let lines = |text: &str| -> Vec<String> {
+        let mut lines: Vec<String> = text
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect();
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+        lines
+    };
+    let (expected, actual) = (lines(expected), lines(actual));
+    if expected == actual {
+        return None;
+    }
+
+    // Longest common subsequence table, filled from the end.
+    let (n, m) = (expected.len(), actual.len());
+    let mut common = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = match expected[i] == actual[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            out.push_str(&format!(" {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || common[i][j + 1] >= common[i + 1][j]) {
+            out.push_str(&format!("+{}\n", actual[j]));
+            j += 1;
+        } else {
+            out.push_str(&format!("-{}\n", expected[i]));
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+/// Runs `job` for `0..count` on all available cores, keeping the order of results.
+fn parallel<T, F>(count: usize, job: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(count);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<T>>>());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= count {
+                    break;
+                }
+                let result = job(index);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every job ran"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(code: &str) -> Snippet {
+        Snippet {
+            path: PathBuf::from("chapter.md"),
+            line: 40,
+            info: "rust".into(),
+            code: code.into(),
+            origins: Vec::new(),
+            output: None,
+        }
+    }
+
+    #[test]
+    fn maps_diagnostics_to_chapter_lines() {
+        let program = harness::assemble(&[], &snippet("struct A { a: i32 }\nlet a = A {};\n"));
+        let stderr = "snippet_0.rs:3:9: error[E0063]: missing field `a` in initializer of `A`\nerror: aborting due to 1 previous error\n";
+        assert_eq!(
+            parse_diagnostics(stderr, "snippet_0.rs", &program),
+            vec![Diagnostic {
+                line: Some(41),
+                message: "error[E0063]: missing field `a` in initializer of `A`".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn compiles_valid_and_rejects_invalid_snippets() {
+        let compiler = Compiler::new().unwrap();
+        let ok = harness::assemble(&[], &snippet("let x: i32 = 1;\nprintln!(\"{x}\");\n"));
+        assert_eq!(compiler.build("ok", &ok).unwrap(), Vec::new());
+
+        let bad = harness::assemble(&[], &snippet("let x: i32 = \"one\";\n"));
+        let diagnostics = compiler.build("bad", &bad).unwrap();
+        assert_eq!(diagnostics[0].line, Some(40));
+        assert!(diagnostics[0].message.contains("E0308"), "{diagnostics:?}");
+    }
+
+    fn verdict(code: &str, expect: Expect) -> Verdict {
+        let compiler = Compiler::new().unwrap();
+        let program = harness::assemble(&[], &snippet(code));
+        judge(&compiler, "judged", &program, &expect, None).unwrap()
+    }
+
+    #[test]
+    fn verifies_declared_expectations() {
+        assert_eq!(verdict("let x = 1;\n", Expect::Run), Verdict::Passed);
+        assert_eq!(
+            verdict("let x: u8 = ();\n", Expect::Ignore),
+            Verdict::Ignored
+        );
+        assert_eq!(
+            verdict("panic!(\"boom\");\n", Expect::ShouldPanic),
+            Verdict::Passed
+        );
+        assert_eq!(
+            verdict("let x = 1;\n", Expect::ShouldPanic),
+            Verdict::Failed(Failure::NoPanic)
+        );
+        assert_eq!(
+            verdict("panic!(\"boom\");\n", Expect::NoRun),
+            Verdict::Passed
+        );
+        assert!(matches!(
+            verdict("panic!(\"boom\");\n", Expect::Run),
+            Verdict::Failed(Failure::Run { stderr, .. }) if stderr.contains("boom")
+        ));
+    }
+
+    #[test]
+    fn checks_compile_fail_error_codes() {
+        let mismatched = "let x: u8 = ();\n";
+        assert_eq!(
+            verdict(mismatched, Expect::CompileFail(vec!["E0308".into()])),
+            Verdict::Passed
+        );
+        assert!(matches!(
+            verdict(mismatched, Expect::CompileFail(vec!["E0119".into()])),
+            Verdict::Failed(Failure::MissingCodes { .. })
+        ));
+        assert_eq!(
+            verdict("let x = 1;\n", Expect::CompileFail(Vec::new())),
+            Verdict::Failed(Failure::Compiled)
+        );
+    }
+
+    #[test]
+    fn compares_stdout_with_expected_output() {
+        let compiler = Compiler::new().unwrap();
+        let program = harness::assemble(
+            &[],
+            &snippet("for i in 1..=3 {\n    println!(\"n{i}\");\n}\n"),
+        );
+        let output = |text: &str| Output {
+            line: 50,
+            text: text.into(),
+        };
+        let judged = |expected: &Output| {
+            judge(
+                &compiler,
+                "printing",
+                &program,
+                &Expect::Run,
+                Some(expected),
+            )
+            .unwrap()
+        };
+        assert_eq!(judged(&output("n1\nn2  \nn3\n\n")), Verdict::Passed);
+        assert_eq!(
+            judged(&output("n1\nn3\nn4\n")),
+            Verdict::Failed(Failure::Output {
+                line: 50,
+                diff: " n1\n+n2\n n3\n-n4\n".into(),
+            })
+        );
+        assert!(matches!(
+            judge(
+                &compiler,
+                "printing",
+                &program,
+                &Expect::NoRun,
+                Some(&output(""))
+            )
+            .unwrap(),
+            Verdict::Failed(Failure::Directive(_))
+        ));
+    }
+
+    #[test]
+    fn parallel_keeps_order() {
+        assert_eq!(parallel(5, |i| i * 2), vec![0, 2, 4, 6, 8]);
+    }
+}
@@ -0,0 +1,665 @@
+//! Turns snippet fragments into complete programs.
+//!
+//! A snippet in the notes is rarely a whole crate: it may be a lone `impl`
+//! relying on a type from an earlier block, or a few `let` statements
+//! showing usage. The harness splits a snippet into top-level items and
+//! statements, places items from the preceding snippets of the same
+//! chapter in front of it, moves loose statements into a generated
+//! `fn main`, and adds `use` lines for well-known std names.
+
+use std::collections::HashSet;
+
+use crate::snippet::{Directives, Snippet};
+
+/// Well-known std names the harness imports when a snippet uses them
+/// without a `use` line.
+pub(crate) const STD_NAMES: &[(&str, &str)] = &[
+    ("fmt", "std::fmt"),
+    ("Display", "std::fmt::Display"),
+    ("Debug", "std::fmt::Debug"),
+    ("FromStr", "std::str::FromStr"),
+    ("Add", "std::ops::Add"),
+    ("Sub", "std::ops::Sub"),
+    ("Mul", "std::ops::Mul"),
+    ("Neg", "std::ops::Neg"),
+    ("Index", "std::ops::Index"),
+    ("IndexMut", "std::ops::IndexMut"),
+    ("Deref", "std::ops::Deref"),
+    ("HashMap", "std::collections::HashMap"),
+    ("HashSet", "std::collections::HashSet"),
+    ("Rc", "std::rc::Rc"),
+    ("RefCell", "std::cell::RefCell"),
+];
+
+/// A generated program together with a map back to the Markdown source.
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub source: String,
+    origins: Vec<Option<usize>>,
+}
+
+impl Program {
+    /// A program written out in full, with no chapter lines behind it.
+    pub fn from_source(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let origins = vec![None; source.lines().count()];
+        Program { source, origins }
+    }
+
+    /// The chapter line a 1-based program line came from, if any.
+    pub fn origin(&self, line: usize) -> Option<usize> {
+        line.checked_sub(1)
+            .and_then(|index| self.origins.get(index).copied().flatten())
+    }
+
+    /// Appends a chunk of snippet code.
+    fn push(&mut self, chunk: &Chunk) {
+        for (line, origin) in chunk.text.lines().zip(&chunk.origins) {
+            self.source.push_str(line);
+            self.source.push('\n');
+            self.origins.push(Some(*origin));
+        }
+    }
+
+    /// Appends a line written by the harness itself.
+    fn push_generated(&mut self, line: &str) {
+        self.source.push_str(line);
+        self.source.push('\n');
+        self.origins.push(None);
+    }
+}
+
+/// Builds the program for `snippet`, using items from `context` (the
+/// snippets that precede it in the same chapter).
+pub fn assemble(context: &[Snippet], snippet: &Snippet) -> Program {
+    let current = chunks(snippet);
+    let own_keys: HashSet<&str> = current.iter().filter_map(Chunk::key).collect();
+
+    // Later definitions replace earlier ones with the same name, so a
+    // chapter can show two versions of the same trait. Imports are replaced
+    // by name, so that `use std::thread;` after `use std::thread::{self,
+    // Thread};` does not import `thread` twice.
+    let mut items: Vec<Chunk> = Vec::new();
+    for chunk in context.iter().flat_map(chunks) {
+        let Some(key) = chunk.key() else { continue };
+        if key == "fn main" || own_keys.contains(key) {
+            continue;
+        }
+        let imported = chunk.imports();
+        items = items
+            .into_iter()
+            .filter(|item| item.key() != Some(key))
+            .filter_map(|item| item.without_imports(&imported))
+            .collect();
+        items.push(chunk);
+    }
+    let own_imports: HashSet<String> = current.iter().flat_map(Chunk::imports).collect();
+    let items: Vec<Chunk> = items
+        .into_iter()
+        .filter_map(|item| item.without_imports(&own_imports))
+        .collect();
+
+    let has_main = own_keys.contains("fn main");
+    let (own_items, statements): (Vec<&Chunk>, Vec<&Chunk>) = current
+        .iter()
+        .filter(|chunk| chunk.kind != Kind::Trivia)
+        .partition(|chunk| has_main || chunk.key().is_some());
+
+    let mut program = Program::default();
+    for path in missing_imports(items.iter().chain(current.iter())) {
+        program.push_generated(&format!("use {path};"));
+    }
+    for chunk in items.iter().chain(own_items) {
+        program.push(chunk);
+    }
+    if !has_main {
+        program.push_generated("fn main() {");
+        for chunk in statements {
+            program.push(chunk);
+        }
+        program.push_generated("}");
+    }
+    program
+}
+
+/// Builds the program of every snippet of a chapter, each in the context of
+/// the snippets before it that share their items. A snippet whose directives
+/// do not parse gets the parse error instead.
+pub fn assemble_chapter(snippets: &[Snippet]) -> Vec<Result<(Directives, Program), String>> {
+    let directives: Vec<_> = snippets.iter().map(Snippet::directives).collect();
+    let shares: Vec<bool> = directives
+        .iter()
+        .map(|directives| directives.as_ref().is_ok_and(Directives::shares_items))
+        .collect();
+    directives
+        .into_iter()
+        .enumerate()
+        .map(|(index, directives)| {
+            let directives = directives?;
+            let context: Vec<Snippet> = match directives.standalone {
+                true => Vec::new(),
+                false => snippets[..index]
+                    .iter()
+                    .zip(&shares)
+                    .filter(|(_, shares)| **shares)
+                    .map(|(snippet, _)| snippet.clone())
+                    .collect(),
+            };
+            Ok((directives, assemble(&context, &snippets[index])))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    /// A top-level item, keyed by what it defines (e.g. `struct Counter`).
+    Item(String),
+    Statement,
+    /// Only whitespace and comments.
+    Trivia,
+}
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    text: String,
+    /// Chapter line of each line of `text`.
+    origins: Vec<usize>,
+    kind: Kind,
+    /// `text` with comments and literal contents blanked out.
+    masked: String,
+}
+
+impl Chunk {
+    fn key(&self) -> Option<&str> {
+        match &self.kind {
+            Kind::Item(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Names this chunk brings into scope.
+    fn defines(&self) -> Vec<String> {
+        let Some(key) = self.key() else {
+            return Vec::new();
+        };
+        match key.strip_prefix("use ") {
+            Some(tree) => imported_names(tree),
+            None if leading_word(key) == "impl" => Vec::new(),
+            None => key
+                .split_once(' ')
+                .map(|(_, name)| vec![name.to_string()])
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Names a `use` chunk imports, without globs and `as _` imports, which
+    /// never clash.
+    fn imports(&self) -> HashSet<String> {
+        match self.key().and_then(|key| key.strip_prefix("use ")) {
+            Some(tree) => imported_names(tree)
+                .into_iter()
+                .filter(|name| name != "*" && name != "_")
+                .collect(),
+            None => HashSet::new(),
+        }
+    }
+
+    /// This chunk without its imports of `names`, or `None` if that leaves a
+    /// `use` with nothing to import. A `use` that keeps some of its imports
+    /// is written out again on one line.
+    fn without_imports(self, names: &HashSet<String>) -> Option<Chunk> {
+        if self.imports().is_disjoint(names) {
+            return Some(self);
+        }
+        let tree = self.key()?.strip_prefix("use ")?;
+        let (prefix, group) = tree.split_once('{')?;
+        let kept: Vec<&str> = group
+            .trim_end_matches('}')
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .filter(|part| {
+                imported_names(&format!("{prefix}{{{part}}}"))
+                    .iter()
+                    .all(|name| !names.contains(name))
+            })
+            .collect();
+        if kept.is_empty() {
+            return None;
+        }
+        // Attributes and the visibility stay in front of the `use`
+        let at = keyword_at(&self.masked, "use").unwrap_or(0);
+        let text = format!("{}use {prefix}{{{}}};", &self.text[..at], kept.join(", "));
+        Some(Chunk {
+            origins: vec![self.origins[0]; text.lines().count()],
+            kind: Kind::Item(format!("use {prefix}{{{}}}", kept.join(", "))),
+            masked: mask(&text),
+            text,
+        })
+    }
+}
+
+/// Item keywords whose definitions end with a closing brace.
+const BRACED_ITEMS: &[&str] = &[
+    "fn",
+    "struct",
+    "enum",
+    "trait",
+    "impl",
+    "mod",
+    "union",
+    "macro_rules",
+    "extern",
+];
+/// Item keywords whose definitions end with a semicolon.
+const PLAIN_ITEMS: &[&str] = &["use", "type", "const", "static"];
+
+/// Splits a snippet into top-level chunks.
+fn chunks(snippet: &Snippet) -> Vec<Chunk> {
+    let code = &snippet.code;
+    let masked = mask(code);
+    let bytes = masked.as_bytes();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+
+    let finish = |start: usize, end: usize, chunks: &mut Vec<Chunk>| {
+        let text = &code[start..end];
+        if text.trim().is_empty() {
+            return;
+        }
+        let lead = text.len() - text.trim_start().len();
+        let begin = start + lead;
+        let kind = classify(&masked[begin..end]);
+        let text = code[begin..end].trim_end();
+        let first = code[..begin].matches('\n').count();
+        chunks.push(Chunk {
+            text: text.to_string(),
+            origins: (first..first + text.lines().count())
+                .map(|offset| snippet.origin(offset))
+                .collect(),
+            kind,
+            masked: masked[begin..end].to_string(),
+        });
+    };
+
+    for (index, byte) in bytes.iter().enumerate() {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    if let Kind::Item(key) = classify(&masked[start..index]) {
+                        if BRACED_ITEMS.contains(&leading_word(&key)) {
+                            finish(start, index + 1, &mut chunks);
+                            start = index + 1;
+                        }
+                    }
+                }
+            }
+            b';' if depth == 0 => {
+                finish(start, index + 1, &mut chunks);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < code.len() {
+        finish(start, code.len(), &mut chunks);
+    }
+    chunks
+}
+
+/// Classifies a chunk by looking past attributes and qualifiers for an item keyword.
+fn classify(masked: &str) -> Kind {
+    let mut rest = masked.trim_start();
+    if rest.is_empty() {
+        return Kind::Trivia;
+    }
+    while let Some(attribute) = rest.strip_prefix('#') {
+        let attribute = attribute.strip_prefix('!').unwrap_or(attribute);
+        let Some(end) = matching(attribute, b'[', b']') else {
+            break;
+        };
+        rest = attribute[end + 1..].trim_start();
+    }
+    loop {
+        let word = leading_word(rest);
+        let after = rest[word.len()..].trim_start();
+        match word {
+            "pub" => {
+                rest = match after.starts_with('(') {
+                    true => matching(after, b'(', b')')
+                        .map_or(after, |end| after[end + 1..].trim_start()),
+                    false => after,
+                };
+            }
+            "async" | "unsafe" | "default" => rest = after,
+            "extern" if leading_word(after) == "crate" => {
+                return Kind::Item(format!(
+                    "extern crate {}",
+                    leading_word(after[5..].trim_start())
+                ));
+            }
+            "const" if leading_word(after) == "fn" => rest = after,
+            "use" => return Kind::Item(normalize(rest.split(';').next().unwrap_or(""))),
+            "impl" | "extern" => {
+                return Kind::Item(normalize(rest.split('{').next().unwrap_or("")))
+            }
+            keyword if BRACED_ITEMS.contains(&keyword) || PLAIN_ITEMS.contains(&keyword) => {
+                let name = leading_word(after.trim_start_matches('!').trim_start());
+                return Kind::Item(format!("{keyword} {name}"));
+            }
+            _ => return Kind::Statement,
+        }
+    }
+}
+
+/// Names a `use` tree such as `std::fmt::{self, Display}` brings into scope.
+fn imported_names(tree: &str) -> Vec<String> {
+    let last = |path: &str| -> String {
+        let path = path.trim();
+        match path.split_once(" as ") {
+            Some((_, alias)) => alias.trim().to_string(),
+            None => path.rsplit("::").next().unwrap_or("").trim().to_string(),
+        }
+    };
+    match tree.split_once('{') {
+        Some((prefix, group)) => group
+            .trim_end_matches('}')
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| match part {
+                "self" => last(prefix.trim_end_matches("::")),
+                part => last(part),
+            })
+            .collect(),
+        None => vec![last(tree)],
+    }
+}
+
+/// `use` paths for well-known std names that appear in code but are neither
+/// defined nor imported by it.
+fn missing_imports<'a>(chunks: impl Iterator<Item = &'a Chunk>) -> Vec<&'static str> {
+    let mut used = HashSet::new();
+    let mut defined = HashSet::new();
+    for chunk in chunks {
+        used.extend(words(&chunk.masked).map(str::to_string));
+        defined.extend(chunk.defines());
+    }
+    STD_NAMES
+        .iter()
+        .filter(|(name, _)| used.contains(*name) && !defined.contains(*name))
+        .map(|(_, path)| *path)
+        .collect()
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+}
+
+/// Byte offset of the first `keyword` in `text` that is a word of its own.
+fn keyword_at(text: &str, keyword: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(keyword).map(|(at, _)| at).find(|&at| {
+        !text[..at].ends_with(is_ident) && !text[at + keyword.len()..].starts_with(is_ident)
+    })
+}
+
+fn leading_word(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Index of the bracket closing the one `text` starts with.
+fn matching(text: &str, open: u8, close: u8) -> Option<usize> {
+    let mut depth = 0;
+    for (index, byte) in text.bytes().enumerate() {
+        if byte == open {
+            depth += 1;
+        } else if byte == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(index);
+            }
+        }
+    }
+    None
+}
+
+/// Replaces comments and the contents of string and char literals with
+/// spaces, keeping byte offsets and newlines intact.
+pub(crate) fn mask(code: &str) -> String {
+    let bytes = code.as_bytes();
+    let mut out = bytes.to_vec();
+    let blank = |out: &mut Vec<u8>, from: usize, to: usize| {
+        for byte in &mut out[from..to] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        if rest.starts_with(b"//") {
+            let end = code[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            blank(&mut out, i, end);
+            i = end;
+        } else if rest.starts_with(b"/*") {
+            let mut depth = 0;
+            let mut j = i;
+            while j < bytes.len() {
+                if bytes[j..].starts_with(b"/*") {
+                    depth += 1;
+                    j += 2;
+                } else if bytes[j..].starts_with(b"*/") {
+                    depth -= 1;
+                    j += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    j += 1;
+                }
+            }
+            blank(&mut out, i, j);
+            i = j;
+        } else if let Some(hashes) = raw_string_start(bytes, i) {
+            let open = i + rest.iter().position(|b| *b == b'"').unwrap_or(0);
+            let closing = format!("\"{}", "#".repeat(hashes));
+            let end = code[open + 1..]
+                .find(&closing)
+                .map_or(bytes.len(), |n| open + 1 + n);
+            blank(&mut out, open + 1, end);
+            i = (end + closing.len()).min(bytes.len());
+        } else if bytes[i] == b'"' {
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != b'"' {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            let end = j.min(bytes.len());
+            blank(&mut out, i + 1, end);
+            i = end + 1;
+        } else if bytes[i] == b'\'' {
+            // A char literal, unless this is a lifetime such as `'a`.
+            let len = code[i + 1..].chars().next().map_or(0, char::len_utf8);
+            let end = if rest.get(1) == Some(&b'\\') {
+                code[i + 2..].find('\'').map(|n| i + 2 + n)
+            } else if bytes.get(i + 1 + len) == Some(&b'\'') {
+                Some(i + 1 + len)
+            } else {
+                None
+            };
+            match end {
+                Some(end) => {
+                    blank(&mut out, i + 1, end);
+                    i = end + 1;
+                }
+                None => i += 1,
+            }
+        } else {
+            i += 1;
+        }
+    }
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// Number of `#`s if a raw string literal starts at `i`.
+fn raw_string_start(bytes: &[u8], i: usize) -> Option<usize> {
+    let preceded_by_ident = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+    if preceded_by_ident {
+        return None;
+    }
+    let rest = bytes[i..].strip_prefix(b"b").unwrap_or(&bytes[i..]);
+    let rest = rest.strip_prefix(b"r")?;
+    let hashes = rest.iter().take_while(|b| **b == b'#').count();
+    (rest.get(hashes) == Some(&b'"')).then_some(hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn snippet(line: usize, code: &str) -> Snippet {
+        Snippet {
+            path: PathBuf::from("chapter.md"),
+            line,
+            info: "rust".into(),
+            code: code.into(),
+            origins: Vec::new(),
+            output: None,
+        }
+    }
+
+    #[test]
+    fn masks_comments_and_literals() {
+        let masked = mask("let s = \"{ // }\"; // note\nlet c = '{'; fn f<'a>() {}");
+        assert_eq!(
+            masked,
+            "let s = \"      \";        \nlet c = ' '; fn f<'a>() {}"
+        );
+    }
+
+    #[test]
+    fn splits_items_from_statements() {
+        let chunks = chunks(&snippet(
+            10,
+            "#[derive(Debug)]\nstruct Counter {\n    count: i32,\n}\n\nlet c = Counter { count: 1 };\nprintln!(\"{:?}\", c);\n",
+        ));
+        let kinds: Vec<_> = chunks
+            .iter()
+            .map(|chunk| (chunk.origins[0], chunk.kind.clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (10, Kind::Item("struct Counter".into())),
+                (15, Kind::Statement),
+                (16, Kind::Statement),
+            ]
+        );
+    }
+
+    #[test]
+    fn keys_impls_by_header() {
+        let chunks = chunks(&snippet(
+            1,
+            "impl<T> Trait for  Vec<T>\nwhere T: Clone\n{\n}\n",
+        ));
+        assert_eq!(
+            chunks[0].kind,
+            Kind::Item("impl<T> Trait for Vec<T> where T: Clone".into())
+        );
+    }
+
+    #[test]
+    fn reads_names_from_use_trees() {
+        assert_eq!(imported_names("std::fmt"), vec!["fmt"]);
+        assert_eq!(
+            imported_names("std::fmt::{self, Display as Show}"),
+            vec!["fmt", "Show"]
+        );
+    }
+
+    #[test]
+    fn wraps_statements_in_main_after_context() {
+        let context = [snippet(3, "pub struct A;\nfn main() {}\n")];
+        let program = assemble(
+            &context,
+            &snippet(
+                20,
+                "impl Default for A {\n    fn default() -> Self { A }\n}\nlet a = A::default();\n",
+            ),
+        );
+        assert_eq!(
+            program.source,
+            "pub struct A;\nimpl Default for A {\n    fn default() -> Self { A }\n}\nfn main() {\nlet a = A::default();\n}\n"
+        );
+        assert_eq!(program.origin(1), Some(3));
+        assert_eq!(program.origin(3), Some(21));
+        assert_eq!(program.origin(5), None);
+        assert_eq!(program.origin(6), Some(23));
+    }
+
+    #[test]
+    fn later_definitions_replace_earlier_ones() {
+        let context = [
+            snippet(1, "trait T { fn f(&self); }\nstruct S;\n"),
+            snippet(5, "trait T { fn f(&self) {} }\n"),
+        ];
+        let program = assemble(&context, &snippet(9, "impl T for S {}\n"));
+        assert_eq!(
+            program.source,
+            "struct S;\ntrait T { fn f(&self) {} }\nimpl T for S {}\nfn main() {\n}\n"
+        );
+    }
+
+    #[test]
+    fn later_imports_replace_earlier_ones_by_name() {
+        let context = [
+            snippet(1, "use std::thread::{self, Thread};\nuse super::*;\n"),
+            snippet(5, "use std::time::Instant;\nuse crate::*;\n"),
+        ];
+        let program = assemble(
+            &context,
+            &snippet(
+                9,
+                "use std::thread;\nuse std::time::{Duration, Instant};\nlet t: Option<Thread> = None;\n",
+            ),
+        );
+        assert_eq!(
+            program.source,
+            "use std::thread::{Thread};\nuse super::*;\nuse crate::*;\nuse std::thread;\n\
+             use std::time::{Duration, Instant};\nfn main() {\nlet t: Option<Thread> = None;\n}\n"
+        );
+        assert_eq!(program.origin(1), Some(1));
+    }
+
+    #[test]
+    fn imports_well_known_std_names() {
+        let program = assemble(
+            &[],
+            &snippet(1, "fn f<T: Display>(t: T) -> String { format!(\"{t}\") }\n"),
+        );
+        assert!(program.source.starts_with("use std::fmt::Display;\n"));
+
+        let program = assemble(
+            &[],
+            &snippet(1, "use std::fmt::Display;\nfn f<T: Display>(_: T) {}\n"),
+        );
+        assert!(!program.source.contains("use std::fmt::Display;\nuse"));
+        assert_eq!(program.source.matches("Display;").count(), 1);
+    }
+}
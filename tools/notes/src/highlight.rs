@@ -0,0 +1,259 @@
+//! Syntax highlighting of Rust snippets for the HTML site.
+//!
+//! A small lexer splits the code into tokens and wraps the interesting ones
+//! in `<span class="...">`. It only has to be good enough for the notes, so
+//! it knows nothing about grammar: an identifier followed by `!` is a macro,
+//! a capitalised identifier is a type.
+
+/// Rust keywords, strict and reserved ones that appear in the notes.
+pub(crate) const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+/// Escapes text for use in HTML element content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders Rust code as escaped HTML with highlighting spans.
+pub fn rust(code: &str) -> String {
+    let chars: Vec<char> = code.chars().collect();
+    let mut html = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let class = if c == '/' && chars.get(i + 1) == Some(&'/') {
+            i = until(&chars, i, |c| c == '\n');
+            "comment"
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i = block_comment_end(&chars, i);
+            "comment"
+        } else if c == '"' {
+            i = string_end(&chars, i + 1);
+            "string"
+        } else if let Some(end) = raw_or_byte_string(&chars, i) {
+            i = end;
+            "string"
+        } else if c == '\'' {
+            match char_literal_end(&chars, i) {
+                Some(end) => {
+                    i = end;
+                    "string"
+                }
+                None => {
+                    i = until(&chars, i + 1, |c| !is_ident(c));
+                    "lifetime"
+                }
+            }
+        } else if c == '#' && matches!(chars.get(i + 1), Some('[' | '!')) {
+            i = attribute_end(&chars, i);
+            "attribute"
+        } else if c.is_ascii_digit() {
+            i = until(&chars, i, |c| !(is_ident(c) || c == '.'));
+            // `1..5` is two numbers and a range, not a float.
+            if let Some(dot) = chars[start..i].iter().position(|&c| c == '.') {
+                if chars
+                    .get(start + dot + 1)
+                    .is_some_and(|c| !c.is_ascii_digit())
+                {
+                    i = start + dot;
+                }
+            }
+            "number"
+        } else if is_ident(c) {
+            i = until(&chars, i, |c| !is_ident(c));
+            let word: String = chars[start..i].iter().collect();
+            if KEYWORDS.contains(&word.as_str()) {
+                "keyword"
+            } else if chars.get(i) == Some(&'!') && chars.get(i + 1) != Some(&'=') {
+                i += 1;
+                "macro"
+            } else if word.starts_with(|c: char| c.is_ascii_uppercase()) {
+                "type"
+            } else {
+                ""
+            }
+        } else {
+            i += 1;
+            ""
+        };
+        let text: String = chars[start..i].iter().collect();
+        match class {
+            "" => html.push_str(&escape(&text)),
+            class => {
+                html.push_str(&format!("<span class=\"{class}\">{}</span>", escape(&text)));
+            }
+        }
+    }
+    html
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The index of the first character from `from` matching `stop`.
+fn until(chars: &[char], from: usize, stop: impl Fn(char) -> bool) -> usize {
+    chars[from..]
+        .iter()
+        .position(|&c| stop(c))
+        .map_or(chars.len(), |offset| from + offset)
+}
+
+fn block_comment_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('/', Some('*')) => {
+                depth += 1;
+                i += 2;
+            }
+            ('*', Some('/')) => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// The index after the closing quote of a string whose body starts at `from`.
+fn string_end(chars: &[char], from: usize) -> usize {
+    let mut i = from;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// The end of a `b"..."`, `r"..."`, `r#"..."#` or `br"..."` literal at `start`.
+fn raw_or_byte_string(chars: &[char], start: usize) -> Option<usize> {
+    if start > 0 && is_ident(chars[start - 1]) {
+        return None;
+    }
+    let mut i = start;
+    if chars[i] == 'b' {
+        i += 1;
+        if chars.get(i) == Some(&'"') {
+            return Some(string_end(chars, i + 1));
+        }
+    }
+    if chars.get(i) != Some(&'r') {
+        return None;
+    }
+    i += 1;
+    let hashes = until(chars, i, |c| c != '#') - i;
+    if chars.get(i + hashes) != Some(&'"') {
+        return None;
+    }
+    i += hashes + 1;
+    while i < chars.len() {
+        if chars[i] == '"'
+            && chars[i + 1..]
+                .iter()
+                .take(hashes)
+                .filter(|&&c| c == '#')
+                .count()
+                == hashes
+        {
+            return Some(i + 1 + hashes);
+        }
+        i += 1;
+    }
+    Some(chars.len())
+}
+
+/// The end of a character literal at `start`, or `None` for a lifetime.
+fn char_literal_end(chars: &[char], start: usize) -> Option<usize> {
+    match chars.get(start + 1)? {
+        '\\' => Some(until(chars, start + 2, |c| c == '\'') + 1),
+        _ if chars.get(start + 2) == Some(&'\'') => Some(start + 3),
+        _ => None,
+    }
+}
+
+fn attribute_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    for (offset, &c) in chars[start..].iter().enumerate() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + offset + 1;
+                }
+            }
+            '\n' => return start + offset,
+            _ => {}
+        }
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_keywords_types_macros_and_literals() {
+        assert_eq!(
+            rust("let x: Option<i32> = Some(1); println!(\"<{}>\", x);"),
+            "<span class=\"keyword\">let</span> x: <span class=\"type\">Option</span>&lt;i32&gt; = \
+             <span class=\"type\">Some</span>(<span class=\"number\">1</span>); \
+             <span class=\"macro\">println!</span>(<span class=\"string\">&quot;&lt;{}&gt;&quot;</span>, x);"
+        );
+    }
+
+    #[test]
+    fn tells_lifetimes_from_char_literals() {
+        assert_eq!(
+            rust("fn f<'a>(c: &'a str) -> char { 'x' }"),
+            "<span class=\"keyword\">fn</span> f&lt;<span class=\"lifetime\">'a</span>&gt;(c: \
+             &amp;<span class=\"lifetime\">'a</span> str) -&gt; char { <span class=\"string\">'x'</span> }"
+        );
+        assert_eq!(rust("'\\n'"), "<span class=\"string\">'\\n'</span>");
+    }
+
+    #[test]
+    fn keeps_comments_attributes_and_raw_strings_whole() {
+        assert_eq!(
+            rust("#[derive(Debug)] // fn \"x\"\n/* a /* b */ c */ r#\"say \"hi\"\"#"),
+            "<span class=\"attribute\">#[derive(Debug)]</span> \
+             <span class=\"comment\">// fn &quot;x&quot;</span>\n\
+             <span class=\"comment\">/* a /* b */ c */</span> \
+             <span class=\"string\">r#&quot;say &quot;hi&quot;&quot;#</span>"
+        );
+    }
+
+    #[test]
+    fn ranges_are_not_floats() {
+        assert_eq!(
+            rust("1..5"),
+            "<span class=\"number\">1</span>..<span class=\"number\">5</span>"
+        );
+        assert_eq!(rust("2.5"), "<span class=\"number\">2.5</span>");
+    }
+}
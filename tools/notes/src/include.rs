@@ -0,0 +1,284 @@
+//! `{{#include path:anchor}}` directives that splice code from the example crates.
+//!
+//! A directive on a line of its own is replaced by the file it names, or by
+//! the region of that file between `// ANCHOR: name` and
+//! `// ANCHOR_END: name`. Paths are relative to the chapter file. Anchor
+//! lines themselves are never included, and the region is dedented so code
+//! nested in a module reads the same as top-level code.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A chapter with its include directives expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expanded {
+    pub text: String,
+    /// The chapter line of each line of `text`. Included lines map to the
+    /// line of their directive.
+    pub origins: Vec<usize>,
+}
+
+impl Expanded {
+    /// The chapter line of a 1-based line of the expanded text.
+    pub fn origin(&self, line: usize) -> usize {
+        line.checked_sub(1)
+            .and_then(|index| self.origins.get(index).copied())
+            .unwrap_or(line)
+    }
+}
+
+/// A directive that could not be expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeError {
+    pub chapter: PathBuf,
+    /// Line of the directive.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.chapter.display(),
+            self.line,
+            self.message
+        )
+    }
+}
+
+impl Error for IncludeError {}
+
+/// A parsed `{{#include ...}}` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive<'a> {
+    pub path: &'a str,
+    pub anchor: Option<&'a str>,
+}
+
+impl<'a> Directive<'a> {
+    /// Parses a line consisting only of an include directive.
+    pub fn parse(line: &'a str) -> Option<Self> {
+        let inner = line
+            .trim()
+            .strip_prefix("{{#include")?
+            .strip_suffix("}}")?
+            .trim();
+        if inner.is_empty() {
+            return None;
+        }
+        Some(match inner.split_once(':') {
+            Some((path, anchor)) => Directive {
+                path: path.trim(),
+                anchor: Some(anchor.trim()),
+            },
+            None => Directive {
+                path: inner,
+                anchor: None,
+            },
+        })
+    }
+}
+
+/// Expands every include directive in `source`, the contents of `chapter`.
+///
+/// Every broken directive is reported, not just the first one.
+pub fn expand(chapter: &Path, source: &str) -> Result<Expanded, Vec<IncludeError>> {
+    let base = chapter.parent().unwrap_or(Path::new(""));
+    let mut expanded = Expanded {
+        text: String::new(),
+        origins: Vec::new(),
+    };
+    let mut errors = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let Some(directive) = Directive::parse(line) else {
+            expanded.text.push_str(line);
+            expanded.text.push('\n');
+            expanded.origins.push(number);
+            continue;
+        };
+        let included = fs::read_to_string(base.join(directive.path))
+            .map_err(|err| format!("cannot include {}: {err}", directive.path))
+            .and_then(|file| match directive.anchor {
+                Some(anchor) => region(&file, anchor)
+                    .map_err(|message| format!("{message} in {}", directive.path)),
+                None => Ok(without_anchors(file.lines())),
+            });
+        let region = match included {
+            Ok(region) => region,
+            Err(message) => {
+                errors.push(IncludeError {
+                    chapter: chapter.to_path_buf(),
+                    line: number,
+                    message,
+                });
+                continue;
+            }
+        };
+        let indent = &line[..line.len() - line.trim_start().len()];
+        for included in region.lines() {
+            if !included.is_empty() {
+                expanded.text.push_str(indent);
+            }
+            expanded.text.push_str(included);
+            expanded.text.push('\n');
+            expanded.origins.push(number);
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(expanded),
+        false => Err(errors),
+    }
+}
+
+/// Reads `chapter` and expands its directives, reporting every broken one
+/// in a single error.
+pub fn load(chapter: &Path) -> io::Result<Expanded> {
+    expand(chapter, &fs::read_to_string(chapter)?).map_err(|errors| {
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        io::Error::other(messages.join("\n"))
+    })
+}
+
+/// The dedented lines between `// ANCHOR: name` and `// ANCHOR_END: name`.
+pub fn region(file: &str, name: &str) -> Result<String, String> {
+    let mut lines = file.lines();
+    if !lines.any(|line| anchor_name(line, "ANCHOR:") == Some(name)) {
+        return Err(format!("anchor `{name}` not found"));
+    }
+    let mut body = Vec::new();
+    for line in lines {
+        if anchor_name(line, "ANCHOR_END:") == Some(name) {
+            return Ok(without_anchors(body.into_iter()));
+        }
+        if anchor_name(line, "ANCHOR:") == Some(name) {
+            break;
+        }
+        body.push(line);
+    }
+    Err(format!(
+        "anchor `{name}` is not closed by `// ANCHOR_END: {name}`"
+    ))
+}
+
+/// Names of all anchors opened in `file`.
+pub fn anchors(file: &str) -> Vec<&str> {
+    file.lines()
+        .filter_map(|line| anchor_name(line, "ANCHOR:"))
+        .collect()
+}
+
+fn anchor_name<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    let comment = line.trim().strip_prefix("//")?.trim_start();
+    Some(comment.strip_prefix(marker)?.trim())
+}
+
+fn is_anchor(line: &str) -> bool {
+    anchor_name(line, "ANCHOR:").is_some() || anchor_name(line, "ANCHOR_END:").is_some()
+}
+
+/// Joins lines, dropping anchor markers and the indentation common to all
+/// non-blank lines.
+fn without_anchors<'a>(lines: impl Iterator<Item = &'a str>) -> String {
+    let lines: Vec<&str> = lines.filter(|line| !is_anchor(line)).collect();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(line.get(indent..).unwrap_or("").trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+use std::fmt;
+
+pub mod nested {
+    // ANCHOR: counter
+    pub struct Counter {
+        // ANCHOR: field
+        pub count: i32,
+        // ANCHOR_END: field
+    }
+    // ANCHOR_END: counter
+}
+";
+
+    #[test]
+    fn parses_directives() {
+        assert_eq!(
+            Directive::parse("  {{#include ../examples/a/src/lib.rs:counter}}"),
+            Some(Directive {
+                path: "../examples/a/src/lib.rs",
+                anchor: Some("counter"),
+            })
+        );
+        assert_eq!(
+            Directive::parse("{{#include lib.rs}}"),
+            Some(Directive {
+                path: "lib.rs",
+                anchor: None,
+            })
+        );
+        assert_eq!(Directive::parse("see {{#include lib.rs}}"), None);
+    }
+
+    #[test]
+    fn extracts_dedented_regions_without_nested_anchors() {
+        assert_eq!(
+            region(SOURCE, "counter").unwrap(),
+            "pub struct Counter {\n    pub count: i32,\n}\n"
+        );
+        assert_eq!(region(SOURCE, "field").unwrap(), "pub count: i32,\n");
+        assert_eq!(
+            region(SOURCE, "missing"),
+            Err("anchor `missing` not found".to_string())
+        );
+        assert_eq!(
+            region("// ANCHOR: open\nfn f() {}\n", "open"),
+            Err("anchor `open` is not closed by `// ANCHOR_END: open`".to_string())
+        );
+        assert_eq!(anchors(SOURCE), vec!["counter", "field"]);
+    }
+
+    #[test]
+    fn expands_directives_and_maps_lines() {
+        let dir = std::env::temp_dir().join(format!("notes-include-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("lib.rs"), SOURCE).unwrap();
+        let chapter = dir.join("chapter.md");
+
+        let expanded = expand(&chapter, "```rust\n{{#include lib.rs:counter}}\n```\n").unwrap();
+        assert_eq!(
+            expanded.text,
+            "```rust\npub struct Counter {\n    pub count: i32,\n}\n```\n"
+        );
+        assert_eq!(expanded.origins, vec![1, 2, 2, 2, 3]);
+        assert_eq!(expanded.origin(5), 3);
+
+        let errors = expand(
+            &chapter,
+            "\n{{#include lib.rs:gone}}\n{{#include lib.rs:counter}}\n{{#include nope.rs}}\n",
+        )
+        .unwrap_err();
+        let found: Vec<_> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(found, vec![2, 4]);
+        assert_eq!(errors[0].message, "anchor `gone` not found in lib.rs");
+        assert!(errors[1].message.starts_with("cannot include nope.rs"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,39 @@
+//! Just enough JSON output for the tools, which have no dependencies.
+
+use std::fmt::Write;
+
+/// `text` as a quoted JSON string.
+pub fn string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            // Keeps the output safe to embed in an HTML `<script>`.
+            '<' => quoted.push_str("\\u003c"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(
+            string("say \"hi\"\\\n\t</script>\u{1}"),
+            "\"say \\\"hi\\\"\\\\\\n\\t\\u003c/script>\\u0001\""
+        );
+    }
+}
@@ -0,0 +1,21 @@
+//! Tooling for the Rust By-Example notes.
+//!
+//! The chapters are plain Markdown files at the repository root. This crate
+//! reads them, splices in code from the example crates under `examples/`,
+//! extracts their Rust snippets and checks that the snippets compile the way
+//! the notes claim. It also renders the notes as a static HTML site whose
+//! snippets can be run by a local companion server.
+
+pub mod check;
+pub mod harness;
+pub mod highlight;
+pub mod include;
+pub mod json;
+pub mod lint;
+pub mod markdown;
+pub mod runner;
+pub mod search;
+pub mod server;
+pub mod site;
+pub mod snippet;
+pub mod xref;
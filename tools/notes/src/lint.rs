@@ -0,0 +1,393 @@
+//! Checks the conventions of the notes that the compiler cannot.
+//!
+//! Every chapter starts with a `# Rust Traits - ...` title, every `###`
+//! example has a Rust snippet, fences are tagged and terminated, files end
+//! with a newline, numbered `###` headings count up from 1 within their `##`
+//! section, and the prose is spelled with words from the dictionary.
+//!
+//! The dictionary is the bundled `words.txt` plus the `cSpell.words` of
+//! `.vscode/settings.json`, so a word added for the editor's spell checker is
+//! accepted here too.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::markdown::{self, Block};
+use crate::snippet;
+
+/// Prefix of every chapter title.
+pub const TITLE_PREFIX: &str = "Rust Traits - ";
+
+/// The bundled English and Rust vocabulary of the notes, one word per line.
+const WORDS: &str = include_str!("words.txt");
+
+/// A convention a chapter does not follow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub file: PathBuf,
+    pub line: usize,
+    /// Short name of the rule, e.g. `spelling`.
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: [{}] {}",
+            self.file.display(),
+            self.line,
+            self.rule,
+            self.message
+        )
+    }
+}
+
+/// The words the spell checker accepts, compared case-insensitively.
+#[derive(Debug, Clone, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// The bundled vocabulary.
+    pub fn bundled() -> Self {
+        let mut dictionary = Dictionary::default();
+        for word in WORDS.lines().map(str::trim).filter(|word| !word.is_empty()) {
+            dictionary.add(word);
+        }
+        dictionary
+    }
+
+    /// The bundled vocabulary plus the cSpell words of the project under `root`.
+    pub fn load(root: &Path) -> io::Result<Self> {
+        let mut dictionary = Dictionary::bundled();
+        let settings = root.join(".vscode").join("settings.json");
+        if settings.exists() {
+            for word in cspell_words(&fs::read_to_string(settings)?) {
+                dictionary.add(&word);
+            }
+        }
+        Ok(dictionary)
+    }
+
+    pub fn add(&mut self, word: &str) {
+        self.words.insert(word.to_lowercase());
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        self.words.contains(&word)
+            || (word.strip_suffix("'s")).is_some_and(|word| self.words.contains(word))
+    }
+
+    /// A known word one edit away from `word`, to suggest as a correction.
+    pub fn suggest(&self, word: &str) -> Option<&str> {
+        let word = word.to_lowercase();
+        let mut candidates: Vec<&str> = self
+            .words
+            .iter()
+            .map(String::as_str)
+            .filter(|known| one_edit(&word, known))
+            .collect();
+        candidates.sort_unstable();
+        candidates.first().copied()
+    }
+}
+
+/// Lints every chapter under `root`.
+pub fn lint(root: &Path) -> io::Result<Vec<Lint>> {
+    let dictionary = Dictionary::load(root)?;
+    let mut lints = Vec::new();
+    for chapter in snippet::chapters(root)? {
+        let source = fs::read_to_string(&chapter)?;
+        lints.extend(lint_chapter(&source, &dictionary).into_iter().map(
+            |(line, rule, message)| Lint {
+                file: chapter.clone(),
+                line,
+                rule,
+                message,
+            },
+        ));
+    }
+    Ok(lints)
+}
+
+/// Lints one chapter, returning `(line, rule, message)` for each problem.
+pub fn lint_chapter(source: &str, dictionary: &Dictionary) -> Vec<(usize, &'static str, String)> {
+    let blocks = markdown::parse(source);
+    let mut lints = Vec::new();
+    title(&blocks, &mut lints);
+    examples(&blocks, &mut lints);
+    numbering(&blocks, &mut lints);
+    for block in markdown::flatten(&blocks) {
+        if let Block::Code(code) = block {
+            if code.info.trim().is_empty() {
+                let message = "code fence has no language tag, use `text` for plain output";
+                lints.push((code.line, "untagged-fence", message.to_string()));
+            }
+            if !code.closed {
+                let message = "code fence is never closed";
+                lints.push((code.line, "unterminated-fence", message.to_string()));
+            }
+        }
+    }
+    if !source.is_empty() && !source.ends_with('\n') {
+        let message = "file does not end with a newline";
+        lints.push((source.lines().count(), "final-newline", message.to_string()));
+    }
+    spelling(&blocks, dictionary, &mut lints);
+    lints.sort_by_key(|(line, ..)| *line);
+    lints
+}
+
+fn title(blocks: &[Block], lints: &mut Vec<(usize, &'static str, String)>) {
+    let message = match blocks.first() {
+        Some(Block::Heading { level: 1, text, .. }) if text.starts_with(TITLE_PREFIX) => return,
+        Some(Block::Heading { level: 1, text, .. }) => {
+            format!("title `{text}` does not start with `{TITLE_PREFIX}`")
+        }
+        _ => format!("chapter does not start with a `# {TITLE_PREFIX}...` title"),
+    };
+    lints.push((blocks.first().map_or(1, Block::line), "title", message));
+}
+
+/// Every `###` heading needs a Rust snippet before the next heading of the
+/// same or a higher level.
+fn examples(blocks: &[Block], lints: &mut Vec<(usize, &'static str, String)>) {
+    for (index, block) in blocks.iter().enumerate() {
+        let Block::Heading {
+            level: 3,
+            text,
+            line,
+        } = block
+        else {
+            continue;
+        };
+        let section = blocks[index + 1..]
+            .iter()
+            .take_while(|block| !matches!(block, Block::Heading { level, .. } if *level <= 3));
+        let has_rust = section
+            .flat_map(|block| markdown::flatten(std::slice::from_ref(block)))
+            .any(|block| matches!(block, Block::Code(code) if code.lang() == "rust"));
+        if !has_rust {
+            let message = format!("example `{text}` has no ```rust block");
+            lints.push((*line, "example-code", message));
+        }
+    }
+}
+
+/// Numbered `###` headings count up from 1 within each `##` section.
+fn numbering(blocks: &[Block], lints: &mut Vec<(usize, &'static str, String)>) {
+    let mut expected = 1;
+    for block in blocks {
+        match block {
+            Block::Heading { level, .. } if *level <= 2 => expected = 1,
+            Block::Heading {
+                level: 3,
+                text,
+                line,
+            } => {
+                let Some(number) = heading_number(text) else {
+                    continue;
+                };
+                if number != expected {
+                    let message = format!("heading `{text}` should be numbered {expected}.");
+                    lints.push((*line, "numbering", message));
+                }
+                expected = number + 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The `N` of a heading starting with `N.`.
+fn heading_number(text: &str) -> Option<usize> {
+    let (number, _) = text.split_once(". ")?;
+    number.parse().ok()
+}
+
+fn spelling(
+    blocks: &[Block],
+    dictionary: &Dictionary,
+    lints: &mut Vec<(usize, &'static str, String)>,
+) {
+    let mut check = |line: usize, text: &str| {
+        for (offset, text) in text.lines().enumerate() {
+            for word in words(text) {
+                if dictionary.contains(word) {
+                    continue;
+                }
+                let message = match dictionary.suggest(word) {
+                    Some(known) => format!("unknown word `{word}`, did you mean `{known}`?"),
+                    None => format!("unknown word `{word}`"),
+                };
+                lints.push((line + offset, "spelling", message));
+            }
+        }
+    };
+    for block in markdown::flatten(blocks) {
+        match block {
+            Block::Heading { text, line, .. } | Block::Paragraph { text, line } => {
+                check(*line, text)
+            }
+            Block::Table { header, rows, line } => {
+                check(*line, &header.join(" "));
+                for (offset, row) in rows.iter().enumerate() {
+                    check(line + 2 + offset, &row.join(" "));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The words of a line of prose, without code spans, link targets, URLs and
+/// identifiers such as `snake_case` or `CamelCase` names.
+fn words(text: &str) -> Vec<&str> {
+    let mut prose = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let skip = ["`", "](", "<!--", "http://", "https://"]
+            .iter()
+            .filter_map(|open| rest.find(open).map(|at| (at, *open)))
+            .min();
+        let Some((at, open)) = skip else {
+            prose.push(rest);
+            break;
+        };
+        prose.push(&rest[..at]);
+        let close = match open {
+            "`" => "`",
+            "](" => ")",
+            "<!--" => "-->",
+            _ => " ",
+        };
+        let after = &rest[at + open.len()..];
+        rest = after
+            .find(close)
+            .map_or("", |end| &after[end + close.len()..]);
+    }
+    prose
+        .into_iter()
+        .flat_map(|text| text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\'')))
+        .map(|word| word.trim_matches('\''))
+        .filter(|word| word.chars().all(|c| c.is_alphabetic() || c == '\''))
+        .filter(|word| word.chars().filter(|c| c.is_alphabetic()).count() > 1)
+        .filter(|word| !word.chars().skip(1).any(char::is_uppercase) || is_acronym(word))
+        .collect()
+}
+
+fn is_acronym(word: &str) -> bool {
+    word.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Whether `a` becomes `b` by one insertion, deletion, substitution or swap
+/// of adjacent letters.
+fn one_edit(a: &str, b: &str) -> bool {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    if a == b || a.len().abs_diff(b.len()) > 1 {
+        return false;
+    }
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    a.get(1..) == b.get(1..)
+        || a.get(1..) == Some(b)
+        || Some(a) == b.get(1..)
+        || (a.len() > 1 && b.len() > 1 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..])
+}
+
+/// The `cSpell.words` array of a VS Code settings file.
+fn cspell_words(settings: &str) -> Vec<String> {
+    let Some(start) = settings.find("\"cSpell.words\"") else {
+        return Vec::new();
+    };
+    let after = &settings[start..];
+    let Some(list) = after
+        .find('[')
+        .and_then(|open| Some(&after[open + 1..open + after[open..].find(']')?]))
+    else {
+        return Vec::new();
+    };
+    list.split(',')
+        .map(|word| word.trim().trim_matches('"').to_string())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> Dictionary {
+        let mut dictionary = Dictionary::default();
+        for word in ["rust", "traits", "basic", "example", "an", "the", "numbers"] {
+            dictionary.add(word);
+        }
+        dictionary
+    }
+
+    fn rules(source: &str) -> Vec<(usize, &'static str)> {
+        lint_chapter(source, &dictionary())
+            .into_iter()
+            .map(|(line, rule, _)| (line, rule))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_conventional_chapter() {
+        let source = "# Rust Traits - Basic\n\n## Example\n\n### 1. Example\n```rust\nfn main() {}\n```\n\n### 2. Example\n\n1. The numbers:\n   ```rust\n   let _ = 1;\n   ```\n";
+        assert_eq!(rules(source), vec![]);
+    }
+
+    #[test]
+    fn reports_titles_fences_and_missing_examples() {
+        let source = "# Traits\n\n### Example\n\n```\ntext\n```\n\n```text\nno end";
+        assert_eq!(
+            rules(source),
+            vec![
+                (1, "title"),
+                (3, "example-code"),
+                (5, "untagged-fence"),
+                (9, "unterminated-fence"),
+                (10, "final-newline"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_numbering_per_section() {
+        let source = "# Rust Traits - Basic\n\n## Example\n\n### 1. Example\n```rust\n```\n### 3. Example\n```rust\n```\n\n## Example\n\n### 2. Example\n```rust\n```\n";
+        assert_eq!(rules(source), vec![(8, "numbering"), (14, "numbering")]);
+    }
+
+    #[test]
+    fn spell_checks_prose_but_not_code() {
+        let source = "# Rust Traits - Basic\n\nThe exmaple `mispeled` [numbers](numbrs.md) an HashMap or some_name.\n\n```rust\nlet speling = 1;\n```\n";
+        let lints = lint_chapter(source, &dictionary());
+        let messages: Vec<_> = lints
+            .iter()
+            .map(|(_, _, message)| message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unknown word `exmaple`, did you mean `example`?",
+                "unknown word `or`",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_cspell_words_from_vscode_settings() {
+        let settings = "{\n    \"editor.tabSize\": 4,\n    \"cSpell.words\": [\n        \"monoid\",\n        \"kleisli\"\n    ]\n}\n";
+        assert_eq!(cspell_words(settings), vec!["monoid", "kleisli"]);
+        assert!(one_edit("moniod", "monoid"));
+        assert!(!one_edit("monad", "monoid"));
+    }
+}
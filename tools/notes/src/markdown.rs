@@ -0,0 +1,498 @@
+//! A small block-level Markdown reader.
+//!
+//! The notes only use a handful of Markdown constructs, so this is not a
+//! CommonMark implementation. It keeps the 1-based source line of every
+//! block so tools can point back into the chapter files.
+
+/// A fenced code block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// The info string after the opening fence, e.g. `rust` or `rust,ignore`.
+    pub info: String,
+    /// The block content with the fence indentation removed.
+    pub code: String,
+    /// Line of the opening fence.
+    pub line: usize,
+    /// Indentation of the opening fence (non-zero inside list items).
+    pub indent: usize,
+    /// Whether a closing fence was found before the end of the file.
+    pub closed: bool,
+}
+
+impl CodeBlock {
+    /// Line of the first line of code inside the fence.
+    pub fn code_line(&self) -> usize {
+        self.line + 1
+    }
+
+    /// The language tag, i.e. the first comma or space separated word of the info string.
+    pub fn lang(&self) -> &str {
+        self.info
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .next()
+            .unwrap_or("")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Heading {
+        level: usize,
+        text: String,
+        line: usize,
+    },
+    Code(CodeBlock),
+    Paragraph {
+        text: String,
+        line: usize,
+    },
+    /// An HTML comment such as `<!-- output -->`, kept verbatim.
+    Html {
+        text: String,
+        line: usize,
+    },
+    /// A bulleted or numbered list; each item is a sequence of blocks.
+    List {
+        ordered: bool,
+        items: Vec<Vec<Block>>,
+        line: usize,
+    },
+    /// A pipe table. Cells keep their inline Markdown.
+    Table {
+        header: Vec<String>,
+        rows: Vec<Vec<String>>,
+        line: usize,
+    },
+}
+
+impl Block {
+    pub fn line(&self) -> usize {
+        match self {
+            Block::Heading { line, .. }
+            | Block::Paragraph { line, .. }
+            | Block::Html { line, .. }
+            | Block::List { line, .. }
+            | Block::Table { line, .. } => *line,
+            Block::Code(code) => code.line,
+        }
+    }
+}
+
+/// Splits a Markdown document into blocks.
+pub fn parse(source: &str) -> Vec<Block> {
+    let lines: Vec<&str> = source.lines().collect();
+    parse_lines(&lines, 1, 0)
+}
+
+/// All blocks in document order, including those nested in list items.
+/// A list comes before the blocks of its items.
+pub fn flatten(blocks: &[Block]) -> Vec<&Block> {
+    let mut flat = Vec::new();
+    for block in blocks {
+        flat.push(block);
+        if let Block::List { items, .. } = block {
+            for item in items {
+                flat.extend(flatten(item));
+            }
+        }
+    }
+    flat
+}
+
+/// Parses `lines`, the first of which is line `first` of the document, after
+/// `indent` columns were removed from each of them.
+fn parse_lines(lines: &[&str], first: usize, indent: usize) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Option<(usize, Vec<&str>)> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        let number = first + i;
+
+        if let Some((fence, info)) = opening_fence(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            let fence_indent = line.len() - trimmed.len();
+            let mut code = String::new();
+            let mut closed = false;
+            i += 1;
+            while i < lines.len() {
+                let inner = lines[i];
+                if is_closing_fence(inner.trim_start(), fence) {
+                    closed = true;
+                    break;
+                }
+                code.push_str(strip_indent(inner, fence_indent));
+                code.push('\n');
+                i += 1;
+            }
+            blocks.push(Block::Code(CodeBlock {
+                info: info.to_string(),
+                code,
+                line: number,
+                indent: indent + fence_indent,
+                closed,
+            }));
+        } else if let Some((level, text)) = heading(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Heading {
+                level,
+                text: text.to_string(),
+                line: number,
+            });
+        } else if trimmed.starts_with("<!--") {
+            flush(&mut paragraph, &mut blocks);
+            let mut text = vec![trimmed];
+            while !text.last().is_some_and(|last| last.contains("-->")) && i + 1 < lines.len() {
+                i += 1;
+                text.push(lines[i]);
+            }
+            blocks.push(Block::Html {
+                text: text.join("\n"),
+                line: number,
+            });
+        } else if let Some(marker) = list_marker(line) {
+            flush(&mut paragraph, &mut blocks);
+            let (list, next) = parse_list(lines, i, marker, first, indent);
+            blocks.push(list);
+            i = next;
+            continue;
+        } else if trimmed.starts_with('|')
+            && lines.get(i + 1).is_some_and(|next| is_separator(next))
+        {
+            flush(&mut paragraph, &mut blocks);
+            let header = cells(trimmed);
+            i += 2;
+            let mut rows = Vec::new();
+            while i < lines.len() && lines[i].trim_start().starts_with('|') {
+                rows.push(cells(lines[i].trim()));
+                i += 1;
+            }
+            blocks.push(Block::Table {
+                header,
+                rows,
+                line: number,
+            });
+            continue;
+        } else if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+        } else {
+            paragraph
+                .get_or_insert_with(|| (number, Vec::new()))
+                .1
+                .push(line);
+        }
+        i += 1;
+    }
+    flush(&mut paragraph, &mut blocks);
+    blocks
+}
+
+fn flush(paragraph: &mut Option<(usize, Vec<&str>)>, blocks: &mut Vec<Block>) {
+    if let Some((line, lines)) = paragraph.take() {
+        blocks.push(Block::Paragraph {
+            text: lines.join("\n"),
+            line,
+        });
+    }
+}
+
+/// A list item marker such as `- ` or `2. `.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Marker {
+    ordered: bool,
+    /// Columns before the marker.
+    indent: usize,
+    /// Columns before the item content.
+    content: usize,
+}
+
+fn list_marker(line: &str) -> Option<Marker> {
+    let trimmed = line.trim_start_matches(' ');
+    let indent = line.len() - trimmed.len();
+    let digits = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+    let (ordered, width) = match trimmed.as_bytes() {
+        [b'-' | b'*' | b'+', ..] => (false, 1),
+        _ if (1..10).contains(&digits)
+            && matches!(trimmed.as_bytes().get(digits), Some(b'.' | b')')) =>
+        {
+            (true, digits + 1)
+        }
+        _ => return None,
+    };
+    let rest = &trimmed[width..];
+    let spaces = rest.len() - rest.trim_start_matches(' ').len();
+    if rest.trim().is_empty() || spaces == 0 {
+        return None;
+    }
+    Some(Marker {
+        ordered,
+        indent,
+        content: indent + width + spaces.min(4),
+    })
+}
+
+fn columns(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Parses the list starting at `lines[start]`; returns it with the index of
+/// the first line after it.
+fn parse_list(
+    lines: &[&str],
+    start: usize,
+    marker: Marker,
+    first: usize,
+    indent: usize,
+) -> (Block, usize) {
+    let mut items = Vec::new();
+    let mut i = start;
+    let mut current = marker;
+    loop {
+        let item_start = i;
+        let mut item: Vec<&str> = vec![&lines[i][current.content..]];
+        i += 1;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.trim().is_empty() {
+                let next = lines[i..].iter().position(|line| !line.trim().is_empty());
+                match next.map(|offset| lines[i + offset]) {
+                    Some(next) if columns(next) >= current.content => {
+                        item.push("");
+                        i += 1;
+                    }
+                    _ => break,
+                }
+            } else if columns(line) >= current.content {
+                item.push(&line[current.content..]);
+                i += 1;
+            } else if list_marker(line).is_none()
+                && !item.last().is_some_and(|last| last.trim().is_empty())
+                && heading(line.trim_start()).is_none()
+                && opening_fence(line.trim_start()).is_none()
+            {
+                // A lazy continuation of the item's last paragraph.
+                item.push(line.trim_start());
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        items.push(parse_lines(
+            &item,
+            first + item_start,
+            indent + current.content,
+        ));
+
+        let next = lines[i..]
+            .iter()
+            .position(|line| !line.trim().is_empty())
+            .map(|offset| i + offset);
+        match next.and_then(|next| Some((next, list_marker(lines[next])?))) {
+            Some((next, found))
+                if found.ordered == marker.ordered && found.indent == marker.indent =>
+            {
+                i = next;
+                current = found;
+            }
+            _ => break,
+        }
+    }
+    let list = Block::List {
+        ordered: marker.ordered,
+        items,
+        line: first + start,
+    };
+    (list, i)
+}
+
+/// Whether `line` is a table header separator such as `|---|:--:|`.
+fn is_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('|')
+        && line.contains('-')
+        && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+fn cells(line: &str) -> Vec<String> {
+    let line = line.trim().trim_start_matches('|');
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|')
+        .map(|cell| cell.trim().to_string())
+        .collect()
+}
+
+/// Returns the fence string (e.g. "```") and the info string.
+fn opening_fence(line: &str) -> Option<(&str, &str)> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.chars().take_while(|c| *c == marker).count();
+    if len < 3 {
+        return None;
+    }
+    let info = line[len..].trim();
+    if marker == '`' && info.contains('`') {
+        return None;
+    }
+    Some((&line[..len], info))
+}
+
+fn is_closing_fence(line: &str, fence: &str) -> bool {
+    let marker = fence.chars().next().unwrap_or('`');
+    let len = line.chars().take_while(|c| *c == marker).count();
+    len >= fence.len() && line[len..].trim().is_empty()
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Removes up to `indent` leading spaces.
+fn strip_indent(line: &str, indent: usize) -> &str {
+    let spaces = line.len() - line.trim_start_matches(' ').len();
+    &line[spaces.min(indent)..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_headings_paragraphs_and_code() {
+        let blocks = parse("# Title\n\nSome text\nmore text\n\n```rust\nfn main() {}\n```\n");
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading {
+                    level: 1,
+                    text: "Title".into(),
+                    line: 1
+                },
+                Block::Paragraph {
+                    text: "Some text\nmore text".into(),
+                    line: 3
+                },
+                Block::Code(CodeBlock {
+                    info: "rust".into(),
+                    code: "fn main() {}\n".into(),
+                    line: 6,
+                    indent: 0,
+                    closed: true,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_list_item_indentation_from_fences() {
+        let blocks = parse("1. Item:\n   ```rust\n   .and_then(f)\n     // nested\n   ```\n");
+        let Block::Code(code) = flatten(&blocks)[2] else {
+            panic!("expected a code block, got {blocks:?}");
+        };
+        assert_eq!(code.indent, 3);
+        assert_eq!(code.line, 2);
+        assert_eq!(code.code, ".and_then(f)\n  // nested\n");
+    }
+
+    #[test]
+    fn parses_nested_and_loose_lists() {
+        let source = "Useful for:\n- one\n- two\n  continued\n\n1. **First**:\n   - nested\n\n2. Second\nlazy\n\nAfter\n";
+        let blocks = parse(source);
+        let paragraph = |text: &str, line| Block::Paragraph {
+            text: text.into(),
+            line,
+        };
+        assert_eq!(
+            blocks,
+            vec![
+                paragraph("Useful for:", 1),
+                Block::List {
+                    ordered: false,
+                    items: vec![
+                        vec![paragraph("one", 2)],
+                        vec![paragraph("two\ncontinued", 3)]
+                    ],
+                    line: 2,
+                },
+                Block::List {
+                    ordered: true,
+                    items: vec![
+                        vec![
+                            paragraph("**First**:", 6),
+                            Block::List {
+                                ordered: false,
+                                items: vec![vec![paragraph("nested", 7)]],
+                                line: 7,
+                            },
+                        ],
+                        vec![paragraph("Second\nlazy", 9)],
+                    ],
+                    line: 6,
+                },
+                paragraph("After", 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_pipe_tables() {
+        let blocks = parse("| A | `b` |\n|---|:-:|\n| 1 | 2 |\n");
+        assert_eq!(
+            blocks,
+            vec![Block::Table {
+                header: vec!["A".into(), "`b`".into()],
+                rows: vec![vec!["1".into(), "2".into()]],
+                line: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn emphasis_is_not_a_list_marker() {
+        assert_eq!(list_marker("**Bold**"), None);
+        assert_eq!(list_marker("2024 was"), None);
+        assert!(list_marker("  * item").is_some());
+    }
+
+    #[test]
+    fn reports_unterminated_fences() {
+        let blocks = parse("```rust,ignore\nlet x = 1;\n");
+        let Block::Code(code) = &blocks[0] else {
+            panic!("expected a code block");
+        };
+        assert!(!code.closed);
+        assert_eq!(code.lang(), "rust");
+    }
+
+    #[test]
+    fn keeps_html_comments_as_blocks() {
+        let blocks = parse("text\n<!-- output -->\n<!--\nmulti\n-->\n");
+        assert_eq!(
+            blocks[1..],
+            [
+                Block::Html {
+                    text: "<!-- output -->".into(),
+                    line: 2
+                },
+                Block::Html {
+                    text: "<!--\nmulti\n-->".into(),
+                    line: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_hashes_without_space() {
+        assert_eq!(heading("#[derive(Debug)]"), None);
+        assert_eq!(heading("### 1. Step"), Some((3, "1. Step")));
+    }
+}
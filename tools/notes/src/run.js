@@ -0,0 +1,92 @@
+// Runs snippets on the local `notes-runner` and streams the output into the page.
+(function () {
+  "use strict";
+
+  // Only the pages the runner serves may post to it; it refuses pages opened
+  // from disk.
+  var runner = "http://127.0.0.1:__PORT__/";
+
+  function append(output, text, kind) {
+    var span = document.createElement("span");
+    if (kind) {
+      span.className = kind;
+    }
+    span.textContent = text;
+    output.appendChild(span);
+  }
+
+  function show(output, event) {
+    switch (event.event) {
+      case "stdout":
+        append(output, event.text);
+        break;
+      case "stderr":
+        append(output, event.text, "stderr");
+        break;
+      case "compile_error":
+        append(output, event.text + "\n", "stderr");
+        break;
+      case "error":
+        append(output, "runner error: " + event.text + "\n", "stderr");
+        break;
+      case "truncated":
+        append(output, "\n[output limit reached, program killed]\n", "status");
+        break;
+      case "timed_out":
+        append(output, "\n[time limit reached, program killed]\n", "status");
+        break;
+      case "exit":
+        append(output, event.code === null ? "[killed]\n" : "[exit code " + event.code + "]\n", "status");
+        break;
+    }
+  }
+
+  async function run(button) {
+    var snippet = button.closest(".snippet");
+    var output = snippet.querySelector(".run-output");
+    output.hidden = false;
+    output.textContent = "";
+    if (location.protocol !== "http:") {
+      append(output, "Snippets only run on pages served by the runner. Start it with " +
+        "`cargo run --bin notes-runner` and open " + runner + ".\n", "stderr");
+      return;
+    }
+    button.disabled = true;
+    try {
+      var response = await fetch("/run", {
+        method: "POST",
+        headers: { "Content-Type": "text/plain" },
+        body: snippet.querySelector(".program").value,
+      });
+      if (!response.ok) {
+        throw new Error(response.status + " " + (await response.text()));
+      }
+      var reader = response.body.getReader();
+      var decoder = new TextDecoder();
+      var pending = "";
+      for (;;) {
+        var chunk = await reader.read();
+        if (chunk.done) {
+          break;
+        }
+        pending += decoder.decode(chunk.value, { stream: true });
+        var lines = pending.split("\n");
+        pending = lines.pop();
+        lines.filter(Boolean).forEach(function (line) {
+          show(output, JSON.parse(line));
+        });
+      }
+    } catch (err) {
+      append(output, "Could not run the snippet (" + err.message + "). " +
+        "Start the runner with `cargo run --bin notes-runner`.\n", "stderr");
+    } finally {
+      button.disabled = false;
+    }
+  }
+
+  document.querySelectorAll(".snippet .run").forEach(function (button) {
+    button.addEventListener("click", function () {
+      run(button);
+    });
+  });
+})();
@@ -0,0 +1,330 @@
+//! Compiles and runs code from the site's "Run" buttons.
+//!
+//! Each program is built in its own scratch directory, with a time limit on
+//! `rustc`, and run with an empty environment, no stdin and resource limits:
+//! a wall-clock timeout, a cap on the output read back and, on Unix, CPU
+//! time, address space, file size and process count limits set with
+//! `ulimit`. Both `rustc` and the program run in a process group of their
+//! own, and a timeout kills the whole group. Output is passed on as it
+//! arrives.
+
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::check::{self, Compiler, BUILD_TIMEOUT, RUN_TIMEOUT};
+use crate::harness::Program;
+use crate::json;
+
+/// Largest file a program may write, in `ulimit -f` blocks (512 bytes or
+/// 1 KiB depending on the shell).
+#[cfg(unix)]
+const FILE_BLOCKS: u64 = 2048;
+
+/// Resources a program may use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// Wall-clock time before `rustc` is killed.
+    pub build_timeout: Duration,
+    /// Wall-clock time before the program is killed.
+    pub timeout: Duration,
+    /// CPU time, enforced on Unix only.
+    pub cpu_seconds: u64,
+    /// Address space, enforced on Unix only.
+    pub memory_bytes: u64,
+    /// Processes and threads, enforced on Unix only. The count is per user,
+    /// so it must leave room for the user's other processes, and root is
+    /// exempt.
+    pub processes: u64,
+    /// Combined stdout and stderr passed on before the program is killed.
+    pub output_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            build_timeout: BUILD_TIMEOUT,
+            timeout: RUN_TIMEOUT,
+            cpu_seconds: RUN_TIMEOUT.as_secs(),
+            memory_bytes: 512 << 20,
+            processes: 4096,
+            output_bytes: 64 << 10,
+        }
+    }
+}
+
+/// Something that happened while building or running a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The program did not compile; the text holds rustc's messages.
+    CompileError(String),
+    Stdout(String),
+    Stderr(String),
+    /// The program printed more than [`Limits::output_bytes`] and was killed.
+    Truncated,
+    /// The program ran past [`Limits::timeout`] and was killed.
+    TimedOut,
+    /// The program ended, with `None` when it was killed by a signal.
+    Exit(Option<i32>),
+}
+
+impl Event {
+    /// The event as one line of JSON, as the runner server streams it.
+    pub fn to_json(&self) -> String {
+        let (event, text) = match self {
+            Event::CompileError(text) => ("compile_error", Some(text)),
+            Event::Stdout(text) => ("stdout", Some(text)),
+            Event::Stderr(text) => ("stderr", Some(text)),
+            Event::Truncated => ("truncated", None),
+            Event::TimedOut => ("timed_out", None),
+            Event::Exit(code) => {
+                let code = code.map_or("null".to_string(), |code| code.to_string());
+                return format!("{{\"event\":\"exit\",\"code\":{code}}}");
+            }
+        };
+        match text {
+            Some(text) => format!("{{\"event\":\"{event}\",\"text\":{}}}", json::string(text)),
+            None => format!("{{\"event\":\"{event}\"}}"),
+        }
+    }
+}
+
+/// Builds `source` as a binary crate and runs it, passing each event to
+/// `emit`. A program that compiles always ends with [`Event::Exit`].
+pub fn run(source: &str, limits: &Limits, mut emit: impl FnMut(&Event)) -> io::Result<()> {
+    let compiler = Compiler::new()?;
+    let program = Program::from_source(source);
+    let diagnostics = compiler.build_within("main", &program, limits.build_timeout)?;
+    if !diagnostics.is_empty() {
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        emit(&Event::CompileError(messages.join("\n")));
+        return Ok(());
+    }
+
+    let mut child = check::spawn_in_group(
+        limited(&compiler.executable("main"), limits)
+            .current_dir(compiler.dir())
+            .env_clear()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?;
+    let (sender, receiver) = mpsc::channel();
+    let readers = [
+        forward(child.stdout.take(), sender.clone(), Event::Stdout),
+        forward(child.stderr.take(), sender, Event::Stderr),
+    ];
+    let deadline = Instant::now() + limits.timeout;
+    let mut written = 0;
+    let killed = loop {
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(event) => {
+                let (Event::Stdout(text) | Event::Stderr(text)) = &event else {
+                    continue;
+                };
+                written += text.len();
+                if written > limits.output_bytes {
+                    break Some(Event::Truncated);
+                }
+                emit(&event);
+            }
+            Err(RecvTimeoutError::Timeout) => break Some(Event::TimedOut),
+            Err(RecvTimeoutError::Disconnected) => break None,
+        }
+    };
+    if let Some(event) = &killed {
+        // Children the program forked may hold its pipes open
+        let _ = check::kill_group(&mut child);
+        emit(event);
+    }
+    let status = child.wait()?;
+    for reader in readers {
+        let _ = reader.join();
+    }
+    emit(&Event::Exit(status.code()));
+    Ok(())
+}
+
+/// A command that runs `program` under the CPU, memory, file and process
+/// limits.
+#[cfg(unix)]
+fn limited(program: &Path, limits: &Limits) -> Command {
+    let mut command = Command::new("/bin/sh");
+    // The process limit is `ulimit -u` in bash and `ulimit -p` in dash
+    command
+        .arg("-c")
+        .arg(format!(
+            "ulimit -t {} && ulimit -v {} && ulimit -f {FILE_BLOCKS} && \
+             {{ ulimit -u {processes} || ulimit -p {processes}; }} 2>/dev/null && exec \"$0\"",
+            limits.cpu_seconds,
+            limits.memory_bytes >> 10,
+            processes = limits.processes,
+        ))
+        .arg(program);
+    command
+}
+
+#[cfg(not(unix))]
+fn limited(program: &Path, _limits: &Limits) -> Command {
+    Command::new(program)
+}
+
+/// Sends what `pipe` produces as events until it closes, never splitting a
+/// UTF-8 character across two events.
+fn forward(
+    pipe: Option<impl Read + Send + 'static>,
+    sender: Sender<Event>,
+    event: fn(String) -> Event,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let Some(mut pipe) = pipe else { return };
+        let mut buffer = [0; 4096];
+        let mut pending = Vec::new();
+        loop {
+            let read = match pipe.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            pending.extend_from_slice(&buffer[..read]);
+            let valid = match std::str::from_utf8(&pending) {
+                Ok(text) => text.len(),
+                // An incomplete character at the end waits for the next read.
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                Err(_) => pending.len(),
+            };
+            let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+            pending.drain(..valid);
+            if !text.is_empty() && sender.send(event(text)).is_err() {
+                return;
+            }
+        }
+        if !pending.is_empty() {
+            let _ = sender.send(event(String::from_utf8_lossy(&pending).into_owned()));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(source: &str, limits: &Limits) -> Vec<Event> {
+        let mut events = Vec::new();
+        run(source, limits, |event| events.push(event.clone())).unwrap();
+        events
+    }
+
+    fn stdout(events: &[Event]) -> String {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Stdout(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn streams_output_and_exit_code() {
+        let events = collect(
+            "fn main() { println!(\"out\"); eprintln!(\"err\"); std::process::exit(3); }",
+            &Limits::default(),
+        );
+        assert_eq!(stdout(&events), "out\n");
+        assert!(events.contains(&Event::Stderr("err\n".into())));
+        assert_eq!(events.last(), Some(&Event::Exit(Some(3))));
+    }
+
+    #[test]
+    fn reports_compile_errors() {
+        let events = collect("fn main() { let x: i32 = \"no\"; }", &Limits::default());
+        match events.as_slice() {
+            [Event::CompileError(message)] => assert!(message.contains("E0308"), "{message}"),
+            other => panic!("unexpected events {other:?}"),
+        }
+    }
+
+    #[test]
+    fn kills_programs_that_run_too_long_or_print_too_much() {
+        let limits = Limits {
+            timeout: Duration::from_millis(500),
+            ..Limits::default()
+        };
+        let events = collect("fn main() { loop {} }", &limits);
+        assert_eq!(events, vec![Event::TimedOut, Event::Exit(None)]);
+
+        let limits = Limits {
+            output_bytes: 100,
+            ..Limits::default()
+        };
+        let events = collect("fn main() { loop { println!(\"spam\"); } }", &limits);
+        assert!(stdout(&events).len() <= 100);
+        assert_eq!(
+            events[events.len() - 2..],
+            [Event::Truncated, Event::Exit(None)]
+        );
+    }
+
+    #[test]
+    fn kills_builds_that_take_too_long() {
+        let limits = Limits {
+            build_timeout: Duration::ZERO,
+            ..Limits::default()
+        };
+        let events = collect("fn main() {}", &limits);
+        match events.as_slice() {
+            [Event::CompileError(message)] => {
+                assert!(message.contains("rustc did not finish"), "{message}")
+            }
+            other => panic!("unexpected events {other:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kills_the_children_of_programs_that_time_out() {
+        let limits = Limits {
+            timeout: Duration::from_millis(500),
+            ..Limits::default()
+        };
+        let started = Instant::now();
+        let events = collect(
+            "fn main() { std::process::Command::new(\"/bin/sleep\").arg(\"30\").spawn().unwrap(); loop {} }",
+            &limits,
+        );
+        assert_eq!(events, vec![Event::TimedOut, Event::Exit(None)]);
+        // The sleep holds the output pipes open until it is killed too
+        assert!(started.elapsed() < Duration::from_secs(20));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn limits_memory() {
+        let limits = Limits {
+            memory_bytes: 64 << 20,
+            ..Limits::default()
+        };
+        let events = collect(
+            "fn main() { let v = vec![1u8; 1 << 30]; println!(\"{}\", v.len()); }",
+            &limits,
+        );
+        assert_eq!(stdout(&events), "");
+        assert_ne!(events.last(), Some(&Event::Exit(Some(0))));
+    }
+
+    #[test]
+    fn serializes_events_as_json_lines() {
+        assert_eq!(
+            Event::Stdout("a\"b\n".into()).to_json(),
+            "{\"event\":\"stdout\",\"text\":\"a\\\"b\\n\"}"
+        );
+        assert_eq!(
+            Event::Exit(None).to_json(),
+            "{\"event\":\"exit\",\"code\":null}"
+        );
+        assert_eq!(Event::TimedOut.to_json(), "{\"event\":\"timed_out\"}");
+    }
+}
@@ -0,0 +1,91 @@
+// The search box of the site, over the index in `search-index.js`.
+(function () {
+  "use strict";
+
+  var index = window.notesSearchIndex;
+  var input = document.querySelector(".search");
+  var results = document.querySelector(".search-results");
+  if (!index || !input || !results) {
+    return;
+  }
+  var root = document.body.dataset.root || "";
+
+  function count(text, term) {
+    var found = 0;
+    for (var at = text.indexOf(term); at !== -1; at = text.indexOf(term, at + term.length)) {
+      found++;
+    }
+    return found;
+  }
+
+  // Ranks as `notes-search` does: definitions and impls of a symbol first,
+  // then headings, then how often each word occurs.
+  function score(section, terms) {
+    var heading = section.heading.toLowerCase();
+    var text = section.text.toLowerCase();
+    var code = section.code.toLowerCase();
+    var total = 0;
+    for (var i = 0; i < terms.length; i++) {
+      var term = terms[i];
+      var lower = term.toLowerCase();
+      var points = (section.defines.indexOf(term) !== -1 ? 10 : 0) +
+        (section.implements.indexOf(term) !== -1 ? 6 : 0) +
+        (heading.indexOf(lower) !== -1 ? 5 : 0) +
+        Math.min(count(text, lower), 5) +
+        Math.min(count(code, lower), 5);
+      if (points === 0) {
+        return 0;
+      }
+      total += points;
+    }
+    return total;
+  }
+
+  function preview(section, term) {
+    var lines = (section.text + "\n" + section.code).split("\n");
+    var lower = term.toLowerCase();
+    for (var i = 0; i < lines.length; i++) {
+      if (lines[i].toLowerCase().indexOf(lower) !== -1) {
+        return lines[i].trim();
+      }
+    }
+    return "";
+  }
+
+  function search() {
+    var terms = input.value.split(/[^\w]+/).filter(Boolean);
+    results.textContent = "";
+    results.hidden = terms.length === 0;
+    var hits = index.sections
+      .map(function (section) {
+        return { section: section, score: score(section, terms) };
+      })
+      .filter(function (hit) {
+        return hit.score > 0;
+      })
+      .sort(function (a, b) {
+        return b.score - a.score;
+      })
+      .slice(0, 10);
+    hits.forEach(function (hit) {
+      var item = document.createElement("li");
+      var link = document.createElement("a");
+      link.href = root + hit.section.page + (hit.section.id ? "#" + hit.section.id : "");
+      link.textContent = hit.section.heading;
+      var chapter = document.createElement("small");
+      chapter.textContent = " " + hit.section.chapter;
+      var text = document.createElement("div");
+      text.className = "preview";
+      text.textContent = preview(hit.section, terms[0]);
+      item.append(link, chapter, text);
+      results.appendChild(item);
+    });
+    if (terms.length && !hits.length) {
+      var none = document.createElement("li");
+      none.textContent = "No matches";
+      results.appendChild(none);
+    }
+  }
+
+  input.addEventListener("input", search);
+})();
@@ -0,0 +1,673 @@
+//! Full-text and symbol search over the notes.
+//!
+//! The index has one entry per section: the text under a `##` or `###`
+//! heading, with deeper headings kept in their parent section. Chapters are
+//! indexed with their include directives expanded, so a symbol defined in an
+//! example crate is found in the chapter that shows it.
+//!
+//! A symbol query matches whole identifiers, case-sensitively, and ranks
+//! sections that define or implement the symbol above those that only use
+//! it. A text query matches words in any case; every word must occur in a
+//! section for it to match.
+
+use std::io;
+use std::path::Path;
+
+use crate::harness;
+use crate::highlight::KEYWORDS;
+use crate::include;
+use crate::json;
+use crate::markdown::{self, Block};
+use crate::site::{plain, slashed, Slugs};
+use crate::snippet;
+use crate::xref;
+
+/// Keywords that introduce a named item.
+const ITEM_KEYWORDS: &[&str] = &[
+    "fn", "struct", "enum", "trait", "type", "mod", "const", "static",
+];
+
+/// Longest preview shown for a hit, in characters.
+const PREVIEW_CHARS: usize = 100;
+
+/// A line of a section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// Line in the chapter file. Included code maps to its directive.
+    pub number: usize,
+    pub text: String,
+    /// Whether the line is Rust code rather than prose.
+    pub code: bool,
+}
+
+/// The text under one heading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Chapter path relative to the notes root, with `/` separators.
+    pub chapter: String,
+    pub heading: String,
+    /// Anchor of the heading on the chapter's page, empty for the text
+    /// before the first section.
+    pub id: String,
+    pub line: usize,
+    pub lines: Vec<Line>,
+    /// Names of the items the section's code defines.
+    pub defines: Vec<String>,
+    /// Traits and types the section's code has `impl` blocks for.
+    pub implements: Vec<String>,
+    /// Traits the section's code uses as bounds: after `T:` in generics and
+    /// `where` clauses, and in `impl Trait` types.
+    pub bounds: Vec<String>,
+    /// Traits and types the section's code has generic `impl<..>` blocks for.
+    pub generic_impls: Vec<String>,
+}
+
+impl Section {
+    /// Page of the static site that shows the section.
+    pub fn page(&self) -> String {
+        match self.chapter.strip_suffix(".md") {
+            Some(stem) => format!("{stem}.html"),
+            None => self.chapter.clone(),
+        }
+    }
+
+    fn text(&self, code: bool) -> String {
+        let lines: Vec<&str> = self
+            .lines
+            .iter()
+            .filter(|line| line.code == code)
+            .map(|line| line.text.as_str())
+            .collect();
+        lines.join("\n")
+    }
+}
+
+/// A section matching a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit<'a> {
+    pub section: &'a Section,
+    pub score: usize,
+    /// Chapter line of the preview.
+    pub line: usize,
+    pub preview: String,
+}
+
+/// The sections of every chapter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Index {
+    pub sections: Vec<Section>,
+}
+
+impl Index {
+    /// Indexes every chapter under `root`, without the generated "See also"
+    /// blocks.
+    pub fn build(root: &Path) -> io::Result<Self> {
+        let mut index = Index::default();
+        for chapter in snippet::chapters(root)? {
+            let expanded = include::load(&chapter)?;
+            let relative = slashed(chapter.strip_prefix(root).unwrap_or(&chapter));
+            let text = xref::without_see_also(&expanded.text);
+            index.add_chapter(&relative, &text, |line| expanded.origin(line));
+        }
+        Ok(index)
+    }
+
+    /// Adds the sections of one chapter, mapping lines of `text` to chapter
+    /// lines with `origin`.
+    pub fn add_chapter(&mut self, chapter: &str, text: &str, origin: impl Fn(usize) -> usize) {
+        let blocks = markdown::parse(text);
+        let mut slugs = Slugs::default();
+        let mut sections = Vec::new();
+        let mut current = Section {
+            chapter: chapter.to_string(),
+            heading: chapter.to_string(),
+            id: String::new(),
+            line: 1,
+            lines: Vec::new(),
+            defines: Vec::new(),
+            implements: Vec::new(),
+            bounds: Vec::new(),
+            generic_impls: Vec::new(),
+        };
+        let add = |current: &mut Section, line: usize, text: &str, code: bool| {
+            current.lines.push(Line {
+                number: origin(line),
+                text: text.to_string(),
+                code,
+            });
+        };
+        for block in markdown::flatten(&blocks) {
+            match block {
+                Block::Heading {
+                    level: 1,
+                    text,
+                    line,
+                } if sections.is_empty() && current.lines.is_empty() => {
+                    current.heading = plain(text);
+                    current.line = origin(*line);
+                }
+                Block::Heading {
+                    level: 1,
+                    text,
+                    line,
+                } => add(&mut current, *line, &plain(text), false),
+                Block::Heading { level, text, line } => {
+                    let id = slugs.id(text);
+                    if *level > 3 {
+                        add(&mut current, *line, &plain(text), false);
+                        continue;
+                    }
+                    let next = Section {
+                        heading: plain(text),
+                        id,
+                        line: origin(*line),
+                        lines: Vec::new(),
+                        ..current.clone()
+                    };
+                    sections.push(std::mem::replace(&mut current, next));
+                }
+                Block::Paragraph { text, line } => {
+                    for (offset, text) in text.lines().enumerate() {
+                        add(&mut current, line + offset, text, false);
+                    }
+                }
+                Block::Code(code) => {
+                    for (offset, text) in code.code.lines().enumerate() {
+                        add(
+                            &mut current,
+                            code.code_line() + offset,
+                            text,
+                            code.lang() == "rust",
+                        );
+                    }
+                }
+                Block::Table { header, rows, line } => {
+                    add(&mut current, *line, &header.join(" | "), false);
+                    for (offset, row) in rows.iter().enumerate() {
+                        add(&mut current, line + 2 + offset, &row.join(" | "), false);
+                    }
+                }
+                Block::List { .. } | Block::Html { .. } => {}
+            }
+        }
+        sections.push(current);
+        for mut section in sections {
+            if section.lines.is_empty() {
+                continue;
+            }
+            let items = items(&section.text(true));
+            section.defines = items.defines;
+            section.implements = items.implements;
+            section.bounds = items.bounds;
+            section.generic_impls = items.generic_impls;
+            self.sections.push(section);
+        }
+    }
+
+    /// Sections that define, implement, mention or use the identifier `name`.
+    pub fn symbol(&self, name: &str) -> Vec<Hit<'_>> {
+        let mut hits: Vec<Hit> = self
+            .sections
+            .iter()
+            .filter_map(|section| {
+                let defined = section.defines.iter().any(|item| item == name);
+                let implemented = section.implements.iter().any(|item| item == name);
+                let in_code = count_word(&harness::mask(&section.text(true)), name);
+                let in_prose = count_word(&section.text(false), name);
+                let score = 10 * usize::from(defined)
+                    + 6 * usize::from(implemented)
+                    + 8 * usize::from(count_word(&section.heading, name) > 0)
+                    + in_code.min(5)
+                    + in_prose.min(5);
+                if score == 0 {
+                    return None;
+                }
+                // Prefer the line that defines or implements the symbol.
+                let mentions = |line: &&Line| match line.code {
+                    true => count_word(&harness::mask(&line.text), name) > 0,
+                    false => count_word(&line.text, name) > 0,
+                };
+                let declares = |line: &&Line| {
+                    let masked = harness::mask(&line.text);
+                    ITEM_KEYWORDS
+                        .iter()
+                        .chain(&["impl"])
+                        .any(|keyword| count_word(&masked, keyword) > 0)
+                };
+                let preview = section
+                    .lines
+                    .iter()
+                    .filter(mentions)
+                    .find(|line| line.code && (defined || implemented) && declares(line))
+                    .or_else(|| section.lines.iter().find(mentions));
+                Some(hit(section, score, preview))
+            })
+            .collect();
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+        hits
+    }
+
+    /// Sections containing every word of `query`, in any case.
+    pub fn text(&self, query: &str) -> Vec<Hit<'_>> {
+        let terms: Vec<String> = words(query).map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let mut hits: Vec<Hit> = self
+            .sections
+            .iter()
+            .filter_map(|section| {
+                let heading = section.heading.to_lowercase();
+                let mut score = 0;
+                for term in &terms {
+                    let found = section
+                        .lines
+                        .iter()
+                        .map(|line| count_word(&line.text.to_lowercase(), term))
+                        .sum::<usize>();
+                    let in_heading = count_word(&heading, term) > 0;
+                    if found == 0 && !in_heading {
+                        return None;
+                    }
+                    score += 5 * usize::from(in_heading) + found.min(5);
+                }
+                let matches = |line: &&Line| {
+                    let text = line.text.to_lowercase();
+                    terms
+                        .iter()
+                        .filter(|term| count_word(&text, term) > 0)
+                        .count()
+                };
+                // The first line with the most query words.
+                let best = section
+                    .lines
+                    .iter()
+                    .map(|line| matches(&line))
+                    .max()
+                    .unwrap_or(0);
+                let preview = section
+                    .lines
+                    .iter()
+                    .find(|line| best > 0 && matches(line) == best);
+                Some(hit(section, score, preview))
+            })
+            .collect();
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+        hits
+    }
+
+    /// The index as JSON, for the search box of the static site.
+    pub fn to_json(&self) -> String {
+        let list = |names: &[String]| {
+            let quoted: Vec<String> = names.iter().map(|name| json::string(name)).collect();
+            format!("[{}]", quoted.join(","))
+        };
+        let sections: Vec<String> = self
+            .sections
+            .iter()
+            .map(|section| {
+                format!(
+                    "{{\"chapter\":{},\"page\":{},\"heading\":{},\"id\":{},\"line\":{},\
+                     \"text\":{},\"code\":{},\"defines\":{},\"implements\":{}}}",
+                    json::string(&section.chapter),
+                    json::string(&section.page()),
+                    json::string(&section.heading),
+                    json::string(&section.id),
+                    section.line,
+                    json::string(&section.text(false)),
+                    json::string(&section.text(true)),
+                    list(&section.defines),
+                    list(&section.implements),
+                )
+            })
+            .collect();
+        format!("{{\"sections\":[\n{}\n]}}\n", sections.join(",\n"))
+    }
+}
+
+impl<'a> Hit<'a> {
+    /// The hit as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"chapter\":{},\"heading\":{},\"id\":{},\"line\":{},\"score\":{},\"preview\":{}}}",
+            json::string(&self.section.chapter),
+            json::string(&self.section.heading),
+            json::string(&self.section.id),
+            self.line,
+            self.score,
+            json::string(&self.preview)
+        )
+    }
+}
+
+fn hit<'a>(section: &'a Section, score: usize, preview: Option<&Line>) -> Hit<'a> {
+    let (line, text) = match preview {
+        Some(line) => (line.number, line.text.trim()),
+        None => (section.line, section.heading.as_str()),
+    };
+    let mut preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    if text.chars().count() > PREVIEW_CHARS {
+        preview.push('…');
+    }
+    Hit {
+        section,
+        score,
+        line,
+        preview,
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !is_ident(c))
+        .filter(|word| !word.is_empty())
+}
+
+/// Occurrences of `word` in `text` that are not part of a longer identifier.
+fn count_word(text: &str, word: &str) -> usize {
+    if word.is_empty() {
+        return 0;
+    }
+    text.match_indices(word)
+        .filter(|(start, _)| {
+            let before = text[..*start].chars().next_back();
+            let after = text[start + word.len()..].chars().next();
+            !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+        })
+        .count()
+}
+
+/// What the code of a section declares.
+#[derive(Debug, Default)]
+struct Items {
+    defines: Vec<String>,
+    implements: Vec<String>,
+    bounds: Vec<String>,
+    generic_impls: Vec<String>,
+}
+
+/// The items `code` defines, the traits and types it has `impl` blocks for
+/// and the traits it uses as bounds.
+fn items(code: &str) -> Items {
+    let masked = harness::mask(code);
+    let tokens = tokens(&masked);
+    let mut items = Items::default();
+    let mut depth = 0;
+    // Depth inside the `<..>` of an item header, and whether the tokens are
+    // in a `where` clause: the places where `T:` starts a list of bounds.
+    let mut generics = 0;
+    let mut clause = false;
+    for (i, token) in tokens.iter().enumerate() {
+        let back = |n: usize| i.checked_sub(n).map_or("", |j| tokens[j]);
+        match *token {
+            "{" => depth += 1,
+            "}" => depth -= 1,
+            _ => {}
+        }
+        match *token {
+            "<" if generics > 0 => generics += 1,
+            "<" if back(1) == "impl" || (ITEM_KEYWORDS.contains(&back(2)) && is_name(back(1))) => {
+                generics = 1;
+            }
+            ">" if generics > 0 && back(1) != "-" => generics -= 1,
+            "where" => clause = true,
+            "{" | ";" => clause = false,
+            _ => {}
+        }
+        let next = tokens.get(i + 1).copied().unwrap_or("");
+        // `const N: usize` is a parameter, not a bound.
+        let colon = *token == ":" && back(1) != ":" && next != ":" && back(2) != "const";
+        // `impl` at the start of an item, not `impl Trait` in a signature.
+        let item_start = i == 0 || matches!(back(1), "}" | ";" | "]" | "{");
+        if (colon && (generics == 1 || clause)) || (*token == "impl" && !item_start) {
+            for name in bound_list(&tokens, i + 1) {
+                push(&mut items.bounds, name);
+            }
+        }
+        // A nested `type` is an associated type such as `Iterator::Item`.
+        let associated = *token == "type" && depth > 0;
+        if ITEM_KEYWORDS.contains(token) && is_name(next) && !associated {
+            push(&mut items.defines, next);
+        }
+        if *token != "impl" || !item_start {
+            continue;
+        }
+        let mut j = skip_generics(&tokens, i + 1);
+        let generic = j > i + 1;
+        let (name, after) = path(&tokens, j);
+        if let Some(name) = name {
+            push(&mut items.implements, name);
+            if generic {
+                push(&mut items.generic_impls, name);
+            }
+        }
+        j = skip_generics(&tokens, after);
+        if tokens.get(j) == Some(&"for") {
+            if let (Some(name), _) = path(&tokens, j + 1) {
+                push(&mut items.implements, name);
+            }
+        }
+    }
+    items
+}
+
+fn push(list: &mut Vec<String>, name: &str) {
+    if !list.iter().any(|known| known == name) {
+        list.push(name.to_string());
+    }
+}
+
+/// The traits of the bounds `A + B<T> + Fn(T) -> U + 'a` starting at
+/// `start`, up to the `,`, `>`, `)` or `{` that ends them.
+fn bound_list<'a>(tokens: &[&'a str], start: usize) -> Vec<&'a str> {
+    let mut names = Vec::new();
+    let mut expect = true;
+    let mut nested = 0;
+    let mut i = start;
+    while let Some(token) = tokens.get(i) {
+        match *token {
+            // The arrow of `Fn(T) -> U` does not close anything.
+            "-" if tokens.get(i + 1) == Some(&">") => i += 1,
+            "<" | "(" | "[" => nested += 1,
+            ">" | ")" | "]" if nested > 0 => nested -= 1,
+            "+" if nested == 0 => expect = true,
+            "," | ">" | ")" | "{" | ";" | "=" | "where" if nested == 0 => break,
+            // A lifetime such as `'a`.
+            "'" => i += 1,
+            "?" | "dyn" => {}
+            _ if expect => {
+                let (name, after) = path(tokens, i);
+                names.extend(name);
+                expect = false;
+                i = after;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    names
+}
+
+fn is_name(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_alphabetic() || c == '_') && !KEYWORDS.contains(&token)
+}
+
+/// Identifiers and single punctuation characters of masked code.
+fn tokens(masked: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = masked;
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            c if c.is_whitespace() => {
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            c if is_ident(c) => rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len()),
+            c => c.len_utf8(),
+        };
+        tokens.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// The index after a `<...>` group starting at `start`, or `start`.
+fn skip_generics(tokens: &[&str], start: usize) -> usize {
+    if tokens.get(start) != Some(&"<") {
+        return start;
+    }
+    let mut depth = 0;
+    for (offset, token) in tokens[start..].iter().enumerate() {
+        match *token {
+            "<" => depth += 1,
+            ">" => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + offset + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// The last segment of a path such as `std::ops::Add` starting at `start`,
+/// and the index after the path.
+fn path<'a>(tokens: &[&'a str], start: usize) -> (Option<&'a str>, usize) {
+    let mut name = None;
+    let mut i = start;
+    while let Some(token) = tokens.get(i).filter(|token| is_name(token)) {
+        name = Some(*token);
+        i += 1;
+        if tokens.get(i) == Some(&":") && tokens.get(i + 1) == Some(&":") {
+            i += 2;
+        } else {
+            break;
+        }
+    }
+    (name, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAPTER: &str = "\
+# Rust Traits - Test
+
+## FromStr Trait
+
+Parsing with `FromStr`:
+
+```rust
+use std::str::FromStr;
+
+impl<T> FromStr for Wrapper<T> {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> { todo!() }
+}
+```
+
+## Display Trait
+
+#### Details
+
+Unlike FromStr, formatting never fails.
+
+```rust
+fn show() -> impl std::fmt::Display { 1 }
+// FromStr in a comment
+```
+";
+
+    fn index() -> Index {
+        let mut index = Index::default();
+        index.add_chapter("test.md", CHAPTER, |line| line);
+        index
+    }
+
+    #[test]
+    fn splits_chapters_into_sections() {
+        let index = index();
+        let headings: Vec<_> = index
+            .sections
+            .iter()
+            .map(|section| (section.heading.as_str(), section.id.as_str(), section.line))
+            .collect();
+        assert_eq!(
+            headings,
+            vec![
+                ("FromStr Trait", "fromstr-trait", 3),
+                ("Display Trait", "display-trait", 16)
+            ]
+        );
+        let display = &index.sections[1];
+        assert_eq!(display.lines[0].text, "Details");
+        assert_eq!(display.lines[1].number, 20);
+        assert_eq!(display.page(), "test.html");
+    }
+
+    #[test]
+    fn finds_definitions_and_impls() {
+        let index = index();
+        assert_eq!(index.sections[0].defines, vec!["from_str"]);
+        assert_eq!(index.sections[0].implements, vec!["FromStr", "Wrapper"]);
+        assert_eq!(index.sections[1].defines, vec!["show"]);
+        assert!(index.sections[1].implements.is_empty());
+        assert_eq!(index.sections[0].generic_impls, vec!["FromStr"]);
+    }
+
+    #[test]
+    fn finds_bounds_and_generic_impls() {
+        let items = items(
+            "impl<T, const N: usize> SomeTrait for core::array::IntoIter<T, N>\n\
+             where\n    T: std::fmt::Debug,\n{}\n\
+             fn show<'a, F: Fn(&str) -> Vec<u8> + Send>(f: F, item: &(impl Display + 'a)) {}\n\
+             impl Meters { fn new(value: u32) -> impl Into<u32> { value } }\n",
+        );
+        assert_eq!(items.bounds, vec!["Debug", "Fn", "Send", "Display", "Into"]);
+        assert_eq!(items.generic_impls, vec!["SomeTrait"]);
+        assert_eq!(items.implements, vec!["SomeTrait", "IntoIter", "Meters"]);
+    }
+
+    #[test]
+    fn ranks_symbol_definitions_above_mentions() {
+        let index = index();
+        let hits = index.symbol("FromStr");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].section.heading, "FromStr Trait");
+        assert_eq!(hits[0].preview, "impl<T> FromStr for Wrapper<T> {");
+        assert_eq!(hits[0].line, 10);
+        // Only the prose mention counts, not the comment.
+        assert_eq!(hits[1].score, 1);
+        assert_eq!(hits[1].preview, "Unlike FromStr, formatting never fails.");
+        assert!(index.symbol("fromstr").is_empty());
+        assert!(index.symbol("From").is_empty());
+    }
+
+    #[test]
+    fn text_queries_need_every_word() {
+        let index = index();
+        let hits = index.text("formatting panics");
+        assert!(hits.is_empty());
+        let hits = index.text("Formatting NEVER");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].section.heading, "Display Trait");
+        let hits = index.text("trait");
+        assert_eq!(hits.len(), 2);
+    }
+
+    #[test]
+    fn serializes_the_index() {
+        let json = index().to_json();
+        assert!(json.starts_with("{\"sections\":[\n{\"chapter\":\"test.md\",\"page\":\"test.html\",\"heading\":\"FromStr Trait\",\"id\":\"fromstr-trait\",\"line\":3,"));
+        assert!(
+            json.contains("\"defines\":[\"from_str\"],\"implements\":[\"FromStr\",\"Wrapper\"]}")
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! The local HTTP server behind the site's "Run" buttons.
+//!
+//! `POST /run` takes Rust source as the request body and answers with a
+//! chunked stream of JSON lines, one per [`Event`](crate::runner::Event).
+//! Other `GET` requests are served from the site directory, so the pages and
+//! the runner share an origin. A page on the internet must not be able to run
+//! code on the laptop, so `/run` only accepts requests addressed to this
+//! server by its loopback name (which a DNS-rebound host name is not) and, from
+//! a browser, only from the pages it serves: pages opened from disk send
+//! `Origin: null`, as do sandboxed frames of any site, and are refused.
+//! Only one program is built and run at a time.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::json;
+use crate::runner::{self, Limits};
+
+/// Port the runner listens on and the pages post to when opened from disk.
+pub const DEFAULT_PORT: u16 = 3030;
+
+/// Largest program accepted, in bytes.
+const MAX_SOURCE_BYTES: usize = 256 << 10;
+
+/// Serves the site and runs posted programs.
+#[derive(Debug)]
+pub struct Server {
+    site: Option<PathBuf>,
+    port: u16,
+    limits: Limits,
+    running: Mutex<()>,
+}
+
+/// The parts of an HTTP request the server looks at.
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Server {
+    /// A server listening on `port` of the loopback interface for the pages
+    /// under `site`, if any, running programs within `limits`.
+    pub fn new(site: Option<PathBuf>, port: u16, limits: Limits) -> Self {
+        Server {
+            site,
+            port,
+            limits,
+            running: Mutex::new(()),
+        }
+    }
+
+    /// Handles connections from `listener` until accepting one fails.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let stream = stream?;
+                scope.spawn(move || {
+                    if let Err(err) = self.handle(&stream) {
+                        eprintln!("connection error: {err}");
+                    }
+                });
+            }
+            Ok(())
+        })
+    }
+
+    /// Reads one request from `stream` and writes the response.
+    pub fn handle<S: Read + Write>(&self, mut stream: S) -> io::Result<()> {
+        let request = match read_request(&mut stream)? {
+            Ok(request) => request,
+            Err(status) => {
+                return respond(&mut stream, status, "text/plain", &[], status.as_bytes())
+            }
+        };
+        let path = request.path.split(['?', '#']).next().unwrap_or("");
+        match (request.method.as_str(), path) {
+            (_, "/run") if !self.allowed(&request) => respond(
+                &mut stream,
+                "403 Forbidden",
+                "text/plain",
+                &[],
+                b"origin not allowed",
+            ),
+            ("POST", "/run") => self.run(&mut stream, &request),
+            ("GET" | "HEAD", path) => self.file(&mut stream, path, request.method == "HEAD"),
+            _ => respond(
+                &mut stream,
+                "405 Method Not Allowed",
+                "text/plain",
+                &[],
+                b"method not allowed",
+            ),
+        }
+    }
+
+    fn run(&self, stream: &mut impl Write, request: &Request) -> io::Result<()> {
+        let Ok(source) = std::str::from_utf8(&request.body) else {
+            return respond(
+                stream,
+                "400 Bad Request",
+                "text/plain",
+                &[],
+                b"source is not UTF-8",
+            );
+        };
+        let headers = [
+            ("Transfer-Encoding", "chunked".to_string()),
+            ("Cache-Control", "no-store".to_string()),
+        ];
+        write_head(stream, "200 OK", "application/x-ndjson", &headers)?;
+
+        let _running = self.running.lock().unwrap_or_else(|err| err.into_inner());
+        let mut sent = Ok(());
+        let result = runner::run(source, &self.limits, |event| {
+            if sent.is_ok() {
+                sent = write_chunk(stream, &event.to_json());
+            }
+        });
+        sent?;
+        if let Err(err) = result {
+            let line = format!(
+                "{{\"event\":\"error\",\"text\":{}}}",
+                json::string(&err.to_string())
+            );
+            write_chunk(stream, &line)?;
+        }
+        stream.write_all(b"0\r\n\r\n")?;
+        stream.flush()
+    }
+
+    /// Whether a request to run code comes from a page this server served, or
+    /// from a client that is not a browser and sends no `Origin`. The `Host`
+    /// must name the server, so that a page whose host name was rebound to
+    /// `127.0.0.1` does not pass for one of ours.
+    fn allowed(&self, request: &Request) -> bool {
+        let hosts = [
+            format!("127.0.0.1:{}", self.port),
+            format!("localhost:{}", self.port),
+        ];
+        if !request
+            .header("Host")
+            .is_some_and(|host| hosts.iter().any(|ours| host.eq_ignore_ascii_case(ours)))
+        {
+            return false;
+        }
+        match request.header("Origin") {
+            None => true,
+            Some(origin) => hosts
+                .iter()
+                .any(|ours| origin.eq_ignore_ascii_case(&format!("http://{ours}"))),
+        }
+    }
+
+    fn file(&self, stream: &mut impl Write, path: &str, head: bool) -> io::Result<()> {
+        let file = self.site.as_deref().and_then(|site| site_file(site, path));
+        let Some((body, content_type)) = file.and_then(|file| {
+            let body = fs::read(&file).ok()?;
+            Some((body, content_type(&file)))
+        }) else {
+            return respond(stream, "404 Not Found", "text/plain", &[], b"not found");
+        };
+        let body = if head { &[][..] } else { &body[..] };
+        respond(stream, "200 OK", content_type, &[], body)
+    }
+}
+
+/// Reads a request, or returns the status to answer a bad one with.
+fn read_request(stream: &mut impl Read) -> io::Result<Result<Request, &'static str>> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(Err("400 Bad Request"));
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers: Vec::new(),
+        body: Vec::new(),
+    };
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            request
+                .headers
+                .push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let length = match request.header("Content-Length").map(str::parse::<usize>) {
+        None => 0,
+        Some(Ok(length)) if length <= MAX_SOURCE_BYTES => length,
+        Some(Ok(_)) => return Ok(Err("413 Payload Too Large")),
+        Some(Err(_)) => return Ok(Err("400 Bad Request")),
+    };
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+    Ok(Ok(request))
+}
+
+/// The file under `site` that a request path names, if it stays inside it.
+fn site_file(site: &Path, path: &str) -> Option<PathBuf> {
+    let relative = Path::new(path.trim_start_matches('/'));
+    if !relative
+        .components()
+        .all(|part| matches!(part, Component::Normal(_)))
+    {
+        return None;
+    }
+    let file = site.join(relative);
+    match file.is_dir() {
+        true => Some(file.join("index.html")),
+        false => Some(file),
+    }
+}
+
+fn content_type(file: &Path) -> &'static str {
+    match file.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+fn write_head(
+    stream: &mut impl Write,
+    status: &str,
+    content_type: &str,
+    headers: &[(&str, String)],
+) -> io::Result<()> {
+    let mut head =
+        format!("HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nConnection: close\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())
+}
+
+fn respond(
+    stream: &mut impl Write,
+    status: &str,
+    content_type: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> io::Result<()> {
+    let mut headers = headers.to_vec();
+    headers.push(("Content-Length", body.len().to_string()));
+    write_head(stream, status, content_type, &headers)?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Writes one JSON line as a chunk of a chunked response.
+fn write_chunk(stream: &mut impl Write, line: &str) -> io::Result<()> {
+    write!(stream, "{:x}\r\n{line}\n\r\n", line.len() + 1)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A connection that reads a canned request and records the response.
+    struct Connection {
+        request: io::Cursor<Vec<u8>>,
+        response: Vec<u8>,
+    }
+
+    impl Read for Connection {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.request.read(buf)
+        }
+    }
+
+    impl Write for Connection {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.response.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn exchange(server: &Server, request: &str) -> String {
+        let mut connection = Connection {
+            request: io::Cursor::new(request.as_bytes().to_vec()),
+            response: Vec::new(),
+        };
+        server.handle(&mut connection).unwrap();
+        String::from_utf8(connection.response).unwrap()
+    }
+
+    fn post(origin: &str, body: &str) -> String {
+        post_to("127.0.0.1:3030", origin, body)
+    }
+
+    fn post_to(host: &str, origin: &str, body: &str) -> String {
+        format!(
+            "POST /run HTTP/1.1\r\nHost: {host}\r\n{origin}Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn forbidden(response: &str) -> bool {
+        response.starts_with("HTTP/1.1 403 Forbidden\r\n")
+    }
+
+    #[test]
+    fn streams_run_events_as_chunked_json_lines() {
+        let server = Server::new(None, DEFAULT_PORT, Limits::default());
+        let response = exchange(&server, &post("", "fn main() { println!(\"hi\"); }"));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(response.contains("\r\n{\"event\":\"stdout\",\"text\":\"hi\\n\"}\n\r\n"));
+        assert!(response.ends_with("{\"event\":\"exit\",\"code\":0}\n\r\n0\r\n\r\n"));
+    }
+
+    #[test]
+    fn refuses_other_origins() {
+        let server = Server::new(None, DEFAULT_PORT, Limits::default());
+        let response = exchange(
+            &server,
+            &post("Origin: https://example.com\r\n", "fn main() {}"),
+        );
+        assert!(forbidden(&response), "{response}");
+        let response = exchange(
+            &server,
+            &post("Origin: http://127.0.0.1:3030\r\n", "fn main() {}"),
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(!response.contains("Access-Control-Allow-Origin"));
+    }
+
+    #[test]
+    fn refuses_pages_opened_from_disk() {
+        let server = Server::new(None, DEFAULT_PORT, Limits::default());
+        let response = exchange(&server, &post("Origin: null\r\n", "fn main() {}"));
+        assert!(forbidden(&response), "{response}");
+        assert!(!response.contains("Access-Control-Allow-Origin"));
+    }
+
+    #[test]
+    fn refuses_host_names_rebound_to_the_loopback() {
+        let server = Server::new(None, DEFAULT_PORT, Limits::default());
+        let rebound = post_to(
+            "evil.test:3030",
+            "Origin: http://evil.test:3030\r\n",
+            "fn main() {}",
+        );
+        assert!(forbidden(&exchange(&server, &rebound)));
+        let without_origin = post_to("evil.test:3030", "", "fn main() {}");
+        assert!(forbidden(&exchange(&server, &without_origin)));
+        let other_port = post_to(
+            "127.0.0.1:8080",
+            "Origin: http://127.0.0.1:8080\r\n",
+            "fn main() {}",
+        );
+        assert!(forbidden(&exchange(&server, &other_port)));
+        let named = post_to(
+            "localhost:3030",
+            "Origin: http://localhost:3030\r\n",
+            "fn main() {}",
+        );
+        let response = exchange(&server, &named);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+    }
+
+    #[test]
+    fn serves_site_files_without_escaping_the_site() {
+        let dir = std::env::temp_dir().join(format!("notes-server-{}", std::process::id()));
+        fs::create_dir_all(dir.join("site")).unwrap();
+        fs::write(dir.join("site/index.html"), "<p>index</p>").unwrap();
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        let server = Server::new(Some(dir.join("site")), DEFAULT_PORT, Limits::default());
+
+        let response = exchange(&server, "GET / HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        assert!(response.ends_with("\r\n\r\n<p>index</p>"));
+
+        let response = exchange(&server, "GET /../secret.txt HTTP/1.1\r\n\r\n");
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,649 @@
+//! A static HTML site of the notes.
+//!
+//! Every chapter becomes a page at the same relative path with an `.html`
+//! extension, and the README becomes `index.html`. Pages link to each other
+//! and to `style.css` with relative paths only, so the site can be opened
+//! straight from disk. Chapters are ordered as the README links to them;
+//! chapters it does not mention follow in path order.
+//!
+//! Each snippet that is expected to compile carries the complete program the
+//! checker builds for it, and a "Run" button that posts the program to a
+//! local `notes-runner` (see [`crate::server`]). The sidebar has a search
+//! box over the [`crate::search`] index, which is loaded as a script so that it
+//! works from disk too.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::highlight::{self, escape};
+use crate::markdown::{self, Block, CodeBlock};
+use crate::search::Index;
+use crate::server::DEFAULT_PORT;
+use crate::snippet::{self, Expect, OUTPUT_MARKER};
+use crate::{harness, include};
+
+/// The stylesheet shared by every page.
+pub const STYLE: &str = include_str!("site.css");
+
+/// The script behind the "Run" buttons, with `__PORT__` standing for the
+/// runner's port.
+const RUN_SCRIPT: &str = include_str!("run.js");
+
+/// The script behind the search box.
+const SEARCH_SCRIPT: &str = include_str!("search.js");
+
+/// A rendered page of the site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// Path of the page relative to the site root, such as `traits-basic.html`.
+    pub path: PathBuf,
+    pub title: String,
+    pub html: String,
+}
+
+/// A heading of a page with its anchor id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    pub level: usize,
+    pub text: String,
+    pub id: String,
+}
+
+/// A parsed page before rendering.
+struct Source {
+    path: PathBuf,
+    title: String,
+    blocks: Vec<Block>,
+    /// The program of each Rust snippet, or `None` where it cannot be run.
+    programs: Vec<Option<String>>,
+}
+
+/// The chapters under `root` in reading order: as the README links to them,
+/// then the chapters it does not mention in path order.
+pub fn ordered_chapters(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut chapters = snippet::chapters(root)?;
+    let listed = linked_paths(&markdown::parse(&readme(root)?));
+    chapters.sort_by_key(|chapter| {
+        let relative = slashed(chapter.strip_prefix(root).unwrap_or(chapter));
+        listed
+            .iter()
+            .position(|link| *link == relative)
+            .unwrap_or(listed.len())
+    });
+    Ok(chapters)
+}
+
+fn readme(root: &Path) -> io::Result<String> {
+    let readme = root.join("README.md");
+    match readme.exists() {
+        true => fs::read_to_string(&readme),
+        false => Ok(String::new()),
+    }
+}
+
+/// Renders the README and every chapter under `root`.
+pub fn build(root: &Path) -> io::Result<Vec<Page>> {
+    let mut sources = vec![source(
+        PathBuf::from("index.html"),
+        &readme(root)?,
+        Vec::new(),
+    )];
+    for chapter in &ordered_chapters(root)? {
+        let expanded = include::load(chapter)?;
+        let snippets = snippet::extract(chapter, &expanded.text);
+        let programs = harness::assemble_chapter(&snippets)
+            .into_iter()
+            .map(|assembled| match assembled {
+                Ok((directives, program)) if directives.expect != Expect::Ignore => {
+                    Some(program.source)
+                }
+                _ => None,
+            })
+            .collect();
+        let relative = chapter.strip_prefix(root).unwrap_or(chapter);
+        sources.push(source(
+            relative.with_extension("html"),
+            &expanded.text,
+            programs,
+        ));
+    }
+    Ok((0..sources.len())
+        .map(|index| render(&sources, index))
+        .collect())
+}
+
+/// Writes `pages`, the stylesheet, the scripts and the search index under
+/// `out`. The index is written both as `search-index.json` and as the
+/// `search-index.js` script the pages load.
+pub fn write(pages: &[Page], index: &Index, out: &Path) -> io::Result<()> {
+    fs::create_dir_all(out)?;
+    fs::write(out.join("style.css"), STYLE)?;
+    fs::write(
+        out.join("run.js"),
+        RUN_SCRIPT.replace("__PORT__", &DEFAULT_PORT.to_string()),
+    )?;
+    fs::write(out.join("search.js"), SEARCH_SCRIPT)?;
+    let json = index.to_json();
+    fs::write(
+        out.join("search-index.js"),
+        format!("window.notesSearchIndex = {};\n", json.trim_end()),
+    )?;
+    fs::write(out.join("search-index.json"), json)?;
+    for page in pages {
+        let target = out.join(&page.path);
+        fs::create_dir_all(target.parent().unwrap_or(out))?;
+        fs::write(target, &page.html)?;
+    }
+    Ok(())
+}
+
+fn source(path: PathBuf, text: &str, programs: Vec<Option<String>>) -> Source {
+    let blocks = markdown::parse(text);
+    let title = blocks
+        .iter()
+        .find_map(|block| match block {
+            Block::Heading { level: 1, text, .. } => Some(plain(text)),
+            _ => None,
+        })
+        .unwrap_or_else(|| slashed(&path));
+    Source {
+        path,
+        title,
+        blocks,
+        programs,
+    }
+}
+
+/// Relative Markdown targets of the links in `blocks`, in document order.
+fn linked_paths(blocks: &[Block]) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut add = |text: &str| {
+        for (_, target) in links(text) {
+            let target = target.split('#').next().unwrap_or("");
+            if target.ends_with(".md") && is_relative(target) {
+                paths.push(target.trim_start_matches("./").to_string());
+            }
+        }
+    };
+    for block in markdown::flatten(blocks) {
+        match block {
+            Block::Paragraph { text, .. } | Block::Heading { text, .. } => add(text),
+            Block::Table { header, rows, .. } => {
+                header
+                    .iter()
+                    .chain(rows.iter().flatten())
+                    .for_each(|cell| add(cell));
+            }
+            _ => {}
+        }
+    }
+    paths
+}
+
+/// Every heading of a page below the title, with its anchor id.
+pub fn headings(blocks: &[Block]) -> Vec<Heading> {
+    let mut slugs = Slugs::default();
+    markdown::flatten(blocks)
+        .into_iter()
+        .filter_map(|block| match block {
+            // The page title gets no id, as in the rendered page.
+            Block::Heading { level, text, .. } if *level > 1 => Some(Heading {
+                level: *level,
+                text: plain(text),
+                id: slugs.id(text),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The `##` and `###` headings of a page with their anchor ids.
+pub fn toc(blocks: &[Block]) -> Vec<Heading> {
+    headings(blocks)
+        .into_iter()
+        .filter(|heading| heading.level <= 3)
+        .collect()
+}
+
+/// GitHub-style heading ids, made unique with `-1`, `-2`, ... suffixes.
+#[derive(Default)]
+pub(crate) struct Slugs {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugs {
+    pub(crate) fn id(&mut self, heading: &str) -> String {
+        let slug: String = plain(heading)
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect();
+        let count = self.seen.entry(slug.clone()).or_insert(0);
+        *count += 1;
+        match *count {
+            1 => slug,
+            n => format!("{slug}-{}", n - 1),
+        }
+    }
+}
+
+fn render(sources: &[Source], index: usize) -> Page {
+    let current = &sources[index];
+    let prefix = "../".repeat(current.path.components().count() - 1);
+    let href = |target: &Source| format!("{prefix}{}", slashed(&target.path));
+
+    let mut sidebar = String::new();
+    for (position, source) in sources.iter().enumerate() {
+        let class = match position == index {
+            true => " class=\"current\"",
+            false => "",
+        };
+        sidebar.push_str(&format!(
+            "<li{class}><a href=\"{}\">{}</a>",
+            href(source),
+            escape(&source.title)
+        ));
+        let headings = toc(&source.blocks);
+        if position == index && !headings.is_empty() {
+            sidebar.push_str("\n<ul class=\"toc\">\n");
+            for heading in headings {
+                sidebar.push_str(&format!(
+                    "<li class=\"level-{}\"><a href=\"#{}\">{}</a></li>\n",
+                    heading.level,
+                    heading.id,
+                    escape(&heading.text)
+                ));
+            }
+            sidebar.push_str("</ul>\n");
+        }
+        sidebar.push_str("</li>\n");
+    }
+
+    let mut pager = String::new();
+    if let Some(previous) = index.checked_sub(1).map(|previous| &sources[previous]) {
+        pager.push_str(&format!(
+            "<a rel=\"prev\" href=\"{}\">&larr; {}</a>\n",
+            href(previous),
+            escape(&previous.title)
+        ));
+    }
+    if let Some(next) = sources.get(index + 1) {
+        pager.push_str(&format!(
+            "<a rel=\"next\" href=\"{}\">{} &rarr;</a>\n",
+            href(next),
+            escape(&next.title)
+        ));
+    }
+
+    let mut renderer = Renderer {
+        programs: &current.programs,
+        ..Renderer::default()
+    };
+    renderer.blocks(&current.blocks);
+    let html = format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{title}</title>
+<link rel=\"stylesheet\" href=\"{prefix}style.css\">
+</head>
+<body data-root=\"{prefix}\">
+<nav class=\"sidebar\">
+<input type=\"search\" class=\"search\" placeholder=\"Search the notes\" aria-label=\"Search the notes\">
+<ol class=\"search-results\" hidden></ol>
+<ol class=\"chapters\">
+{sidebar}</ol>
+</nav>
+<main>
+{content}<nav class=\"pager\">
+{pager}</nav>
+</main>
+<script src=\"{prefix}run.js\"></script>
+<script src=\"{prefix}search-index.js\"></script>
+<script src=\"{prefix}search.js\"></script>
+</body>
+</html>
+",
+        title = escape(&current.title),
+        content = renderer.html,
+    );
+    Page {
+        path: current.path.clone(),
+        title: current.title.clone(),
+        html,
+    }
+}
+
+/// Turns blocks into HTML, numbering the Rust snippets as it goes.
+#[derive(Default)]
+struct Renderer<'a> {
+    programs: &'a [Option<String>],
+    html: String,
+    slugs: Slugs,
+    snippets: usize,
+    output_next: bool,
+}
+
+impl Renderer<'_> {
+    fn blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        let output = std::mem::take(&mut self.output_next);
+        match block {
+            Block::Heading { level: 1, text, .. } => {
+                self.html.push_str(&format!("<h1>{}</h1>\n", inline(text)));
+            }
+            Block::Heading { level, text, .. } => {
+                let id = self.slugs.id(text);
+                self.html.push_str(&format!(
+                    "<h{level} id=\"{id}\"><a href=\"#{id}\">{}</a></h{level}>\n",
+                    inline(text)
+                ));
+            }
+            Block::Paragraph { text, .. } => {
+                self.html.push_str(&format!("<p>{}</p>\n", inline(text)));
+            }
+            Block::Html { text, .. } => self.output_next = text.trim() == OUTPUT_MARKER,
+            Block::Code(code) if output => {
+                self.html.push_str(&format!(
+                    "<div class=\"output\"><span class=\"label\">Output</span>\
+                     <pre><code>{}</code></pre></div>\n",
+                    escape(&code.code)
+                ));
+            }
+            Block::Code(code) => self.code(code),
+            Block::List { ordered, items, .. } => {
+                let tag = if *ordered { "ol" } else { "ul" };
+                self.html.push_str(&format!("<{tag}>\n"));
+                for item in items {
+                    match item.as_slice() {
+                        [Block::Paragraph { text, .. }] => {
+                            self.html.push_str(&format!("<li>{}</li>\n", inline(text)));
+                        }
+                        blocks => {
+                            self.html.push_str("<li>\n");
+                            self.blocks(blocks);
+                            self.html.push_str("</li>\n");
+                        }
+                    }
+                }
+                self.html.push_str(&format!("</{tag}>\n"));
+            }
+            Block::Table { header, rows, .. } => {
+                self.html.push_str("<table>\n<thead><tr>");
+                for cell in header {
+                    self.html.push_str(&format!("<th>{}</th>", inline(cell)));
+                }
+                self.html.push_str("</tr></thead>\n<tbody>\n");
+                for row in rows {
+                    self.html.push_str("<tr>");
+                    for cell in row {
+                        self.html.push_str(&format!("<td>{}</td>", inline(cell)));
+                    }
+                    self.html.push_str("</tr>\n");
+                }
+                self.html.push_str("</tbody>\n</table>\n");
+            }
+        }
+    }
+
+    fn code(&mut self, code: &CodeBlock) {
+        if code.lang() != "rust" {
+            let class = match code.lang() {
+                "" => String::new(),
+                lang => format!(" class=\"language-{}\"", escape(lang)),
+            };
+            self.html.push_str(&format!(
+                "<pre><code{class}>{}</code></pre>\n",
+                escape(&code.code)
+            ));
+            return;
+        }
+        self.snippets += 1;
+        let id = format!("snippet-{}", self.snippets);
+        let labels: String = code
+            .info
+            .split([',', ' '])
+            .skip(1)
+            .filter(|directive| !directive.is_empty())
+            .map(|directive| format!("<span class=\"directive\">{}</span>", escape(directive)))
+            .collect();
+        let run = match self.programs.get(self.snippets - 1) {
+            Some(Some(program)) => format!(
+                "<button class=\"run\" type=\"button\">Run</button>\
+                 <textarea class=\"program\" hidden readonly>{}</textarea>",
+                escape(program)
+            ),
+            _ => String::new(),
+        };
+        self.html.push_str(&format!(
+            "<div class=\"snippet\" id=\"{id}\"><a class=\"anchor\" href=\"#{id}\" \
+             title=\"Link to this snippet\">#</a>{labels}{run}\
+             <pre><code class=\"language-rust\">{}</code></pre>\
+             <pre class=\"run-output\" hidden></pre></div>\n",
+            highlight::rust(&code.code)
+        ));
+    }
+}
+
+/// Renders inline Markdown: code spans, `**strong**`, `*emphasis*` and links.
+/// Links to `.md` files are pointed at their `.html` pages.
+pub fn inline(text: &str) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            let fence = &rest[..ticks];
+            if let Some(end) = rest[ticks..].find(fence) {
+                let code = rest[ticks..ticks + end].trim();
+                html.push_str(&format!("<code>{}</code>", escape(code)));
+                rest = &rest[2 * ticks + end..];
+                continue;
+            }
+            html.push_str(&escape(fence));
+            rest = &rest[ticks..];
+            continue;
+        }
+        if let Some(inner) = rest.strip_prefix("**") {
+            if let Some(end) = inner.find("**").filter(|&end| end > 0) {
+                html.push_str(&format!("<strong>{}</strong>", inline(&inner[..end])));
+                rest = &inner[end + 2..];
+                continue;
+            }
+        }
+        if let Some(inner) = rest.strip_prefix('*') {
+            if let Some(end) = inner
+                .find('*')
+                .filter(|&end| end > 0 && !inner.starts_with(' '))
+            {
+                html.push_str(&format!("<em>{}</em>", inline(&inner[..end])));
+                rest = &inner[end + 1..];
+                continue;
+            }
+        }
+        if c == '[' {
+            if let Some((label, target, len)) = link(rest) {
+                html.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape(&html_target(target)),
+                    inline(label)
+                ));
+                rest = &rest[len..];
+                continue;
+            }
+        }
+        if c == '\\' {
+            if let Some(escaped) = rest[1..].chars().next().filter(char::is_ascii_punctuation) {
+                html.push_str(&escape(&escaped.to_string()));
+                rest = &rest[1 + escaped.len_utf8()..];
+                continue;
+            }
+        }
+        html.push_str(&escape(&c.to_string()));
+        rest = &rest[c.len_utf8()..];
+    }
+    html
+}
+
+/// A `[label](target)` link at the start of `text`, with its length.
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let close = text.find("](")?;
+    let end = text[close + 2..].find(')')? + close + 2;
+    let label = &text[1..close];
+    let target = &text[close + 2..end];
+    if label.contains('[') || target.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((label, target, end + 1))
+}
+
+/// Every `[label](target)` link in `text`.
+pub(crate) fn links(text: &str) -> Vec<(&str, &str)> {
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        rest = &rest[start..];
+        match link(rest) {
+            Some((label, target, len)) => {
+                found.push((label, target));
+                rest = &rest[len..];
+            }
+            None => rest = &rest[1..],
+        }
+    }
+    found
+}
+
+pub(crate) fn is_relative(target: &str) -> bool {
+    !target.contains("://") && !target.starts_with(['/', '#']) && !target.starts_with("mailto:")
+}
+
+/// Points a relative link to a chapter at the chapter's page.
+fn html_target(target: &str) -> String {
+    let (path, fragment) = match target.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (target, None),
+    };
+    match path.strip_suffix(".md") {
+        Some(stem) if is_relative(target) => match fragment {
+            Some(fragment) => format!("{stem}.html#{fragment}"),
+            None => format!("{stem}.html"),
+        },
+        _ => target.to_string(),
+    }
+}
+
+/// Heading text without inline markup, for titles and the sidebar.
+pub(crate) fn plain(text: &str) -> String {
+    text.chars().filter(|c| !matches!(c, '`' | '*')).collect()
+}
+
+pub(crate) fn slashed(path: &Path) -> String {
+    let parts: Vec<_> = path
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect();
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_inline_markup() {
+        assert_eq!(
+            inline(
+                "Use `a<b>` with **bold `x`** and *care*, see [basics](traits-basic.md#traits)."
+            ),
+            "Use <code>a&lt;b&gt;</code> with <strong>bold <code>x</code></strong> and \
+             <em>care</em>, see <a href=\"traits-basic.html#traits\">basics</a>."
+        );
+        assert_eq!(inline("````rust ````"), "<code>rust</code>");
+        assert_eq!(inline("2 * 3 and a*"), "2 * 3 and a*");
+        assert_eq!(
+            inline("[docs](https://doc.rust-lang.org/x.md)"),
+            "<a href=\"https://doc.rust-lang.org/x.md\">docs</a>"
+        );
+    }
+
+    #[test]
+    fn numbers_repeated_headings() {
+        let blocks = markdown::parse(
+            "# Title\n## Using `impl` Syntax\n### 1. Using `impl` Syntax\n### 1. Using `impl` Syntax\n#### Deep\n",
+        );
+        let ids: Vec<_> = toc(&blocks).into_iter().map(|heading| heading.id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "using-impl-syntax",
+                "1-using-impl-syntax",
+                "1-using-impl-syntax-1"
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_linked_pages_in_readme_order() {
+        let dir = std::env::temp_dir().join(format!("notes-site-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(
+            dir.join("README.md"),
+            "# Notes\n\n- [Second](nested/b.md)\n- [First](a.md)\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("a.md"),
+            "# A\n\n## Part\n\n```rust\nlet x = 1;\n```\n<!-- output -->\n```text\n1\n```\n\n```rust,ignore\nnope\n```\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("nested/b.md"),
+            "# B\n\n```rust,no_run\nloop {}\n```\n",
+        )
+        .unwrap();
+
+        let pages = build(&dir).unwrap();
+        let paths: Vec<_> = pages.iter().map(|page| slashed(&page.path)).collect();
+        assert_eq!(paths, vec!["index.html", "nested/b.html", "a.html"]);
+
+        let index = &pages[0].html;
+        assert!(index.contains("<a href=\"nested/b.html\">Second</a>"));
+        assert!(index.contains("<a rel=\"next\" href=\"nested/b.html\">B &rarr;</a>"));
+
+        let nested = &pages[1].html;
+        assert!(nested.contains("href=\"../style.css\""));
+        assert!(nested.contains("<a rel=\"prev\" href=\"../index.html\">"));
+        assert!(nested.contains("<a rel=\"next\" href=\"../a.html\">"));
+        assert!(nested.contains("<span class=\"directive\">no_run</span>"));
+        assert!(nested.contains("<script src=\"../run.js\"></script>"));
+
+        let a = &pages[2].html;
+        assert!(a.contains("<li class=\"level-2\"><a href=\"#part\">Part</a></li>"));
+        assert!(a.contains("<h2 id=\"part\">"));
+        assert!(a.contains("id=\"snippet-1\""));
+        assert!(a.contains(
+            "<textarea class=\"program\" hidden readonly>fn main() {\nlet x = 1;\n}\n</textarea>"
+        ));
+        assert!(
+            a.contains("<div class=\"output\"><span class=\"label\">Output</span><pre><code>1\n")
+        );
+        assert!(!a.contains("&lt;!--"));
+        assert_eq!(a.matches("<button class=\"run\"").count(), 1);
+        assert!(!a.contains("rel=\"next\""));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
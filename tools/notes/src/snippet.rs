@@ -0,0 +1,283 @@
+//! Rust snippets extracted from the chapter files.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::include;
+use crate::markdown::{self, Block};
+
+/// A fenced `rust` block from a chapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    /// Chapter file the snippet was taken from.
+    pub path: PathBuf,
+    /// Line of the first line of code (1-based).
+    pub line: usize,
+    /// The full fence info string.
+    pub info: String,
+    pub code: String,
+    /// The chapter line of each line of `code`. Code spliced in by an include
+    /// directive maps to the directive's line.
+    pub origins: Vec<usize>,
+    /// Expected stdout, from an `<!-- output -->` block after the snippet.
+    pub output: Option<Output>,
+}
+
+/// The documented stdout of a snippet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    /// Line of the first line of the expected output.
+    pub line: usize,
+    pub text: String,
+}
+
+/// The comment that marks the fenced block after a snippet as its expected stdout.
+pub const OUTPUT_MARKER: &str = "<!-- output -->";
+
+impl Snippet {
+    /// The chapter line of the 0-based line `offset` of the code.
+    pub fn origin(&self, offset: usize) -> usize {
+        self.origins
+            .get(offset)
+            .copied()
+            .unwrap_or(self.line + offset)
+    }
+
+    /// Parses the directives that follow `rust` in the fence info string.
+    pub fn directives(&self) -> Result<Directives, String> {
+        Directives::parse(&self.info)
+    }
+}
+
+/// What a snippet is expected to do when checked.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Expect {
+    /// Compile and run successfully (the default).
+    #[default]
+    Run,
+    /// Compile, but do not run (`no_run`).
+    NoRun,
+    /// Compile, then panic when run (`should_panic`).
+    ShouldPanic,
+    /// Fail to compile, optionally with the listed error codes (`compile_fail,E0119`).
+    CompileFail(Vec<String>),
+    /// Skip the snippet entirely (`ignore`).
+    Ignore,
+}
+
+/// Fence-info directives, modelled on rustdoc's code block attributes.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Directives {
+    pub expect: Expect,
+    /// Compile without the items of earlier snippets and do not share this
+    /// snippet's items with later ones (`standalone`).
+    pub standalone: bool,
+}
+
+impl Directives {
+    /// Parses an info string such as `rust,compile_fail,E0119`.
+    pub fn parse(info: &str) -> Result<Self, String> {
+        let mut directives = Directives::default();
+        let mut codes = Vec::new();
+        let mut expect = None;
+        let words = info
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|word| !word.is_empty())
+            .skip(1);
+        for word in words {
+            let next = match word {
+                "standalone" => {
+                    directives.standalone = true;
+                    continue;
+                }
+                code if is_error_code(code) => {
+                    codes.push(code.to_string());
+                    continue;
+                }
+                "no_run" => Expect::NoRun,
+                "should_panic" => Expect::ShouldPanic,
+                "compile_fail" => Expect::CompileFail(Vec::new()),
+                "ignore" => Expect::Ignore,
+                other => return Err(format!("unknown snippet directive `{other}`")),
+            };
+            if let Some((_, previous)) = expect.replace((next, word)) {
+                return Err(format!(
+                    "conflicting snippet directives `{previous}` and `{word}`"
+                ));
+            }
+        }
+        directives.expect = match expect.map(|(expect, _)| expect).unwrap_or_default() {
+            Expect::CompileFail(_) => Expect::CompileFail(codes),
+            _ if !codes.is_empty() => {
+                return Err(format!("error codes in `{info}` require `compile_fail`"));
+            }
+            expect => expect,
+        };
+        Ok(directives)
+    }
+
+    /// Whether later snippets in the chapter can build on this one.
+    pub fn shares_items(&self) -> bool {
+        !self.standalone
+            && matches!(
+                self.expect,
+                Expect::Run | Expect::NoRun | Expect::ShouldPanic
+            )
+    }
+}
+
+fn is_error_code(word: &str) -> bool {
+    word.len() == 5 && word.starts_with('E') && word[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Extracts every `rust` code block from a Markdown document.
+///
+/// A rust block directly followed by [`OUTPUT_MARKER`] and a fenced block
+/// gets that block as its expected output:
+///
+/// ````markdown
+/// ```rust
+/// println!("Value: {}", 1);
+/// ```
+/// <!-- output -->
+/// ```text
+/// Value: 1
+/// ```
+/// ````
+pub fn extract(path: &Path, source: &str) -> Vec<Snippet> {
+    let parsed = markdown::parse(source);
+    let blocks = markdown::flatten(&parsed);
+    let mut snippets = Vec::new();
+    for (index, block) in blocks.iter().enumerate() {
+        let Block::Code(code) = block else { continue };
+        if code.lang() != "rust" {
+            continue;
+        }
+        let output = match &blocks[index + 1..] {
+            [Block::Html { text, .. }, Block::Code(output), ..] if text.trim() == OUTPUT_MARKER => {
+                Some(Output {
+                    line: output.code_line(),
+                    text: output.code.clone(),
+                })
+            }
+            _ => None,
+        };
+        snippets.push(Snippet {
+            path: path.to_path_buf(),
+            line: code.code_line(),
+            info: code.info.clone(),
+            code: code.code.clone(),
+            origins: (code.code_line()..)
+                .take(code.code.lines().count())
+                .collect(),
+            output,
+        });
+    }
+    snippets
+}
+
+/// Reads a chapter, expands its include directives and extracts its snippets.
+pub fn load(path: &Path) -> io::Result<Vec<Snippet>> {
+    let expanded = include::load(path)?;
+    let mut snippets = extract(path, &expanded.text);
+    for snippet in &mut snippets {
+        snippet.line = expanded.origin(snippet.line);
+        for origin in &mut snippet.origins {
+            *origin = expanded.origin(*origin);
+        }
+        if let Some(output) = &mut snippet.output {
+            output.line = expanded.origin(output.line);
+        }
+    }
+    Ok(snippets)
+}
+
+/// Directories under the notes root that never contain chapters.
+const SKIPPED_DIRS: &[&str] = &["target", "tools", "examples"];
+
+/// Finds the chapter files under `root`: every Markdown file except the
+/// README, including one level of topic directories such as `traits-functional/`.
+pub fn chapters(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    collect(root, 0, &mut found)?;
+    found.sort();
+    Ok(found)
+}
+
+fn collect(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_dir() {
+            if depth == 0 && !name.starts_with('.') && !SKIPPED_DIRS.contains(&name) {
+                collect(&path, depth + 1, found)?;
+            }
+        } else if name.ends_with(".md") && !name.eq_ignore_ascii_case("README.md") {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_only_rust_blocks() {
+        let source = "# T\n\n```rust\nlet a = 1;\n```\n\n```text\nnope\n```\n\n```rust,ignore\nlet b;\n```\n";
+        let snippets = extract(Path::new("t.md"), source);
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].line, 4);
+        assert_eq!(snippets[0].code, "let a = 1;\n");
+        assert_eq!(snippets[1].info, "rust,ignore");
+        assert_eq!(snippets[1].line, 12);
+    }
+
+    #[test]
+    fn attaches_output_blocks() {
+        let source = "```rust\nprintln!(\"hi\");\n```\n<!-- output -->\n```text\nhi\n```\n\n```rust\nlet a = 1;\n```\n";
+        let snippets = extract(Path::new("t.md"), source);
+        assert_eq!(
+            snippets[0].output,
+            Some(Output {
+                line: 6,
+                text: "hi\n".into()
+            })
+        );
+        assert_eq!(snippets[1].output, None);
+    }
+
+    #[test]
+    fn parses_directives() {
+        assert_eq!(Directives::parse("rust"), Ok(Directives::default()));
+        assert_eq!(
+            Directives::parse("rust,compile_fail,E0119"),
+            Ok(Directives {
+                expect: Expect::CompileFail(vec!["E0119".into()]),
+                standalone: false,
+            })
+        );
+        assert_eq!(
+            Directives::parse("rust, should_panic, standalone"),
+            Ok(Directives {
+                expect: Expect::ShouldPanic,
+                standalone: true,
+            })
+        );
+        assert!(Directives::parse("rust,no_run,ignore").is_err());
+        assert!(Directives::parse("rust,should_pnic").is_err());
+        assert!(Directives::parse("rust,E0119").is_err());
+    }
+
+    #[test]
+    fn only_passing_snippets_share_items() {
+        let shares = |info| Directives::parse(info).unwrap().shares_items();
+        assert!(shares("rust"));
+        assert!(shares("rust,should_panic"));
+        assert!(!shares("rust,compile_fail"));
+        assert!(!shares("rust,ignore"));
+        assert!(!shares("rust,standalone"));
+    }
+}
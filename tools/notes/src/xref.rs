@@ -0,0 +1,561 @@
+//! Cross-references between chapters.
+//!
+//! A trait or type becomes a glossary term when sections of at least two
+//! chapters define it or have an `impl` block for it; a name that only shows
+//! up in a derive, a signature or the prose does not count. Trait bounds and
+//! generic `impl<..>` blocks are terms of their own, so the chapters that use
+//! them link to each other whatever traits they name. Every chapter then ends with a generated
+//! "See also" block linking to the sections of other chapters that share its
+//! terms, and `glossary.md` lists every term with the best section of each
+//! chapter. The generated text is committed, so links that no longer match a
+//! heading are caught by [`broken_links`], which checks every relative
+//! Markdown link in the notes.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::include;
+use crate::markdown::{self, Block};
+use crate::search::{Index, Section};
+use crate::site::{self, plain, slashed};
+
+/// The generated glossary chapter, relative to the notes root.
+pub const GLOSSARY: &str = "glossary.md";
+
+/// First line of the generated block at the end of a chapter.
+pub const SEE_ALSO_START: &str = "<!-- see-also: generated by notes-xref, do not edit -->";
+
+/// Last line of the generated block at the end of a chapter.
+pub const SEE_ALSO_END: &str = "<!-- /see-also -->";
+
+/// A chapter of the notes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    /// Path relative to the notes root, with `/` separators.
+    pub path: String,
+    pub title: String,
+}
+
+/// The section of a chapter that covers a term best.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub chapter: String,
+    pub heading: String,
+    pub id: String,
+    pub line: usize,
+}
+
+/// A trait or type name, or a kind of code, used in more than one chapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub name: String,
+    /// Whether `name` is a Rust name rather than a kind of code such as
+    /// "Trait bounds".
+    pub code: bool,
+    /// One reference per chapter, in reading order.
+    pub references: Vec<Reference>,
+}
+
+impl Term {
+    /// `name` as Markdown, in backticks when it is a Rust name.
+    pub fn label(&self) -> String {
+        match self.code {
+            true => format!("`{}`", self.name),
+            false => self.name.clone(),
+        }
+    }
+}
+
+/// The terms shared between the chapters of the notes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrossReferences {
+    /// Chapters in reading order, without the glossary.
+    pub chapters: Vec<Chapter>,
+    /// Terms in alphabetical order.
+    pub terms: Vec<Term>,
+}
+
+/// A relative link whose file or heading does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    pub file: PathBuf,
+    pub line: usize,
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: broken link to `{}`: {}",
+            self.file.display(),
+            self.line,
+            self.target,
+            self.message
+        )
+    }
+}
+
+impl Error for BrokenLink {}
+
+/// Finds the terms shared by the chapters under `root`.
+pub fn analyze(root: &Path) -> io::Result<CrossReferences> {
+    let mut index = Index::default();
+    let mut chapters = Vec::new();
+    for chapter in site::ordered_chapters(root)? {
+        let path = slashed(chapter.strip_prefix(root).unwrap_or(&chapter));
+        if path == GLOSSARY {
+            continue;
+        }
+        let expanded = include::load(&chapter)?;
+        let text = without_see_also(&expanded.text);
+        index.add_chapter(&path, &text, |line| expanded.origin(line));
+        chapters.push(Chapter {
+            title: title(&text).unwrap_or_else(|| path.clone()),
+            path,
+        });
+    }
+    Ok(CrossReferences {
+        terms: terms(&index, &chapters),
+        chapters,
+    })
+}
+
+/// Whether a section uses a kind of code.
+type Uses = fn(&Section) -> bool;
+
+/// Kinds of code that are terms of their own, with the test for a section
+/// using them.
+const KINDS: &[(&str, Uses)] = &[
+    ("Generic impls", |section| !section.generic_impls.is_empty()),
+    ("Trait bounds", |section| !section.bounds.is_empty()),
+];
+
+/// The terms of `index`, with the best section of each chapter using them.
+pub fn terms(index: &Index, chapters: &[Chapter]) -> Vec<Term> {
+    let is_term =
+        |name: &&str| name.len() > 1 && name.starts_with(|c: char| c.is_ascii_uppercase());
+    let declares = |section: &Section, name: &str| {
+        section
+            .defines
+            .iter()
+            .chain(&section.implements)
+            .any(|item| item == name)
+    };
+    let mut names: Vec<&str> = Vec::new();
+    for section in &index.sections {
+        names.extend(section.defines.iter().map(String::as_str));
+        names.extend(section.implements.iter().map(String::as_str));
+    }
+    names.sort_unstable();
+    names.dedup();
+    let mut terms: Vec<Term> = names
+        .into_iter()
+        .filter(is_term)
+        .filter_map(|name| {
+            // Hits come best first, so the first one of a chapter wins.
+            let sections = index
+                .symbol(name)
+                .into_iter()
+                .map(|hit| hit.section)
+                .filter(|section| declares(section, name));
+            term(name, true, sections, chapters)
+        })
+        .collect();
+    for (name, uses) in KINDS {
+        // The first section of a chapter is the one that introduces it.
+        let sections = index.sections.iter().filter(|section| uses(section));
+        terms.extend(term(name, false, sections, chapters));
+    }
+    terms.sort_by_key(|term| term.name.to_lowercase());
+    terms
+}
+
+/// The term `name` when `sections` cover more than one chapter, with the
+/// first of each chapter.
+fn term<'a>(
+    name: &str,
+    code: bool,
+    sections: impl Iterator<Item = &'a Section>,
+    chapters: &[Chapter],
+) -> Option<Term> {
+    let mut references: Vec<Reference> = Vec::new();
+    for section in sections {
+        if references
+            .iter()
+            .all(|known| known.chapter != section.chapter)
+        {
+            references.push(Reference {
+                chapter: section.chapter.clone(),
+                heading: section.heading.clone(),
+                id: section.id.clone(),
+                line: section.line,
+            });
+        }
+    }
+    let order = |chapter: &str| chapters.iter().position(|known| known.path == chapter);
+    references.sort_by_key(|reference| order(&reference.chapter));
+    (references.len() > 1).then(|| Term {
+        name: name.to_string(),
+        code,
+        references,
+    })
+}
+
+impl CrossReferences {
+    /// The "See also" block for `chapter`, or `None` when it shares no term.
+    pub fn see_also(&self, chapter: &str) -> Option<String> {
+        // Target sections in reading order, each with the shared terms.
+        let mut targets: BTreeMap<(usize, usize), (&Reference, Vec<String>)> = BTreeMap::new();
+        for term in &self.terms {
+            if term
+                .references
+                .iter()
+                .all(|reference| reference.chapter != chapter)
+            {
+                continue;
+            }
+            for reference in &term.references {
+                if reference.chapter == chapter {
+                    continue;
+                }
+                let key = (self.position(&reference.chapter), reference.line);
+                let (_, names) = targets.entry(key).or_insert((reference, Vec::new()));
+                names.push(term.label());
+            }
+        }
+        if targets.is_empty() {
+            return None;
+        }
+        let mut block = format!("{SEE_ALSO_START}\n## See also\n\n");
+        for (reference, names) in targets.values() {
+            block.push_str(&format!(
+                "- {} in *{}*: {}\n",
+                self.link(chapter, reference),
+                self.title(&reference.chapter),
+                names.join(", ")
+            ));
+        }
+        block.push_str(&format!("\n{SEE_ALSO_END}\n"));
+        Some(block)
+    }
+
+    /// The glossary chapter.
+    pub fn glossary(&self) -> String {
+        let mut text = String::from(
+            "# Rust Traits - Glossary\n\n\
+             <!-- generated by notes-xref from the chapters, do not edit -->\n\n\
+             Traits and types defined or implemented in more than one chapter, and\n\
+             the trait bounds and generic impls several chapters use, with the\n\
+             section of each chapter that covers them best.\n",
+        );
+        for term in &self.terms {
+            text.push_str(&format!("\n## {}\n\n", term.label()));
+            for reference in &term.references {
+                text.push_str(&format!(
+                    "- {} in *{}*\n",
+                    self.link(GLOSSARY, reference),
+                    self.title(&reference.chapter)
+                ));
+            }
+        }
+        text
+    }
+
+    /// Every generated file under `root` with the text it should have: the
+    /// chapters with their "See also" blocks, and the glossary.
+    pub fn generated(&self, root: &Path) -> io::Result<Vec<(PathBuf, String)>> {
+        let mut files = Vec::new();
+        for chapter in &self.chapters {
+            let path = root.join(&chapter.path);
+            let source = fs::read_to_string(&path)?;
+            files.push((path, with_see_also(&source, self.see_also(&chapter.path))));
+        }
+        files.push((root.join(GLOSSARY), self.glossary()));
+        Ok(files)
+    }
+
+    fn position(&self, chapter: &str) -> usize {
+        self.chapters
+            .iter()
+            .position(|known| known.path == chapter)
+            .unwrap_or(self.chapters.len())
+    }
+
+    fn title<'a>(&'a self, chapter: &'a str) -> &'a str {
+        self.chapters
+            .iter()
+            .find(|known| known.path == chapter)
+            .map_or(chapter, |known| known.title.as_str())
+    }
+
+    /// A Markdown link from the chapter `from` to `reference`.
+    fn link(&self, from: &str, reference: &Reference) -> String {
+        let depth = from.matches('/').count();
+        let fragment = match reference.id.as_str() {
+            "" => String::new(),
+            id => format!("#{id}"),
+        };
+        format!(
+            "[{}]({}{}{fragment})",
+            reference.heading,
+            "../".repeat(depth),
+            reference.chapter
+        )
+    }
+}
+
+/// `text` without its generated "See also" block.
+pub fn without_see_also(text: &str) -> String {
+    match text.find(SEE_ALSO_START) {
+        Some(start) => format!("{}\n", text[..start].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+/// `source` with its "See also" block replaced by `block`.
+pub fn with_see_also(source: &str, block: Option<String>) -> String {
+    let had_block = source.contains(SEE_ALSO_START);
+    let text = without_see_also(source);
+    match block {
+        Some(block) => format!("{}\n\n{block}", text.trim_end()),
+        // A chapter that never had a block is left exactly as it is.
+        None if !had_block => source.to_string(),
+        None => text,
+    }
+}
+
+/// Every relative link in the README and the chapters under `root` that
+/// names a missing file, or a heading its target does not have.
+pub fn broken_links(root: &Path) -> io::Result<Vec<BrokenLink>> {
+    let mut files = vec![root.join("README.md")];
+    files.extend(crate::snippet::chapters(root)?);
+    let mut broken = Vec::new();
+    for file in files.into_iter().filter(|file| file.exists()) {
+        let source = fs::read_to_string(&file)?;
+        let base = file.parent().unwrap_or(root);
+        for (line, target) in links(&markdown::parse(&source)) {
+            let (path, fragment) = target.split_once('#').unwrap_or((&target, ""));
+            let linked = match path {
+                "" => file.clone(),
+                path => normalize(&base.join(path)),
+            };
+            let message = if !linked.exists() {
+                Some("no such file".to_string())
+            } else if fragment.is_empty() || linked.extension().is_none_or(|ext| ext != "md") {
+                None
+            } else {
+                let text = fs::read_to_string(&linked)?;
+                let ids = site::headings(&markdown::parse(&text));
+                match ids.iter().any(|heading| heading.id == fragment) {
+                    true => None,
+                    false => Some(format!(
+                        "no heading `#{fragment}` in {}",
+                        linked.strip_prefix(root).unwrap_or(&linked).display()
+                    )),
+                }
+            };
+            if let Some(message) = message {
+                broken.push(BrokenLink {
+                    file: file.clone(),
+                    line,
+                    target,
+                    message,
+                });
+            }
+        }
+    }
+    Ok(broken)
+}
+
+/// The relative link targets in `blocks` with their lines.
+fn links(blocks: &[Block]) -> Vec<(usize, String)> {
+    let mut found = Vec::new();
+    let mut add = |line: usize, text: &str| {
+        for (offset, text) in text.lines().enumerate() {
+            for (_, target) in site::links(text) {
+                if site::is_relative(target) || target.starts_with('#') {
+                    found.push((line + offset, target.to_string()));
+                }
+            }
+        }
+    };
+    for block in markdown::flatten(blocks) {
+        match block {
+            Block::Paragraph { text, line } | Block::Heading { text, line, .. } => add(*line, text),
+            Block::Table { header, rows, line } => {
+                add(*line, &header.join(" "));
+                for (offset, row) in rows.iter().enumerate() {
+                    add(line + 2 + offset, &row.join(" "));
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+fn title(text: &str) -> Option<String> {
+    markdown::parse(text)
+        .into_iter()
+        .find_map(|block| match block {
+            Block::Heading { level: 1, text, .. } => Some(plain(&text)),
+            _ => None,
+        })
+}
+
+/// Resolves `.` and `..` components without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for part in path.components() {
+        match part {
+            Component::CurDir => {}
+            Component::ParentDir if normal.file_name().is_some() => {
+                normal.pop();
+            }
+            part => normal.push(part),
+        }
+    }
+    normal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes() -> CrossReferences {
+        let mut index = Index::default();
+        let basic = "# Rust Traits - Basic\n\n## Bounds\n\n```rust\nfn show<T: Display>(t: T) {}\n```\n\n## Own types\n\n```rust\n#[derive(Debug, Clone)]\nstruct Meters(u32);\n```\n";
+        let advanced = "# Rust Traits - Advanced\n\n## Complex types\n\n```rust\nimpl<T, const N: usize> SomeTrait for core::array::IntoIter<T, N>\nwhere\n    T: Debug,\n{}\n```\n";
+        let functional = "# Rust Traits - Functional\n\n## Printing\n\nDisplay a `Vec` of `Meters`:\n\n```rust\nimpl Display for Meters {}\nfn all() -> Vec<Meters> { Vec::new() }\n```\n\n## Chaining\n\n```rust\nimpl<T: Debug, E> MonadicResult<T, E> for Result<T, E> {}\n```\n";
+        index.add_chapter("basic.md", basic, |line| line);
+        index.add_chapter("advanced.md", advanced, |line| line);
+        index.add_chapter("nested/functional.md", functional, |line| line);
+        let chapters = vec![
+            Chapter {
+                path: "basic.md".into(),
+                title: "Rust Traits - Basic".into(),
+            },
+            Chapter {
+                path: "advanced.md".into(),
+                title: "Rust Traits - Advanced".into(),
+            },
+            Chapter {
+                path: "nested/functional.md".into(),
+                title: "Rust Traits - Functional".into(),
+            },
+        ];
+        CrossReferences {
+            terms: terms(&index, &chapters),
+            chapters,
+        }
+    }
+
+    #[test]
+    fn finds_terms_defined_or_implemented_in_several_chapters() {
+        let notes = notes();
+        // `Display` is only a bound in one chapter and `Debug`, `Clone` and
+        // `Vec` are only derived or named, so none of them is a term.
+        let names: Vec<_> = notes.terms.iter().map(|term| term.name.as_str()).collect();
+        assert_eq!(names, vec!["Generic impls", "Meters", "Trait bounds"]);
+        let meters = &notes.terms[1];
+        assert_eq!(meters.references[0].heading, "Own types");
+        assert_eq!(meters.references[1].id, "printing");
+    }
+
+    #[test]
+    fn links_trait_bounds_and_generic_impls() {
+        let notes = notes();
+        let headings = |term: &Term| -> Vec<String> {
+            let headings = term
+                .references
+                .iter()
+                .map(|reference| reference.heading.clone());
+            headings.collect()
+        };
+        assert_eq!(headings(&notes.terms[0]), vec!["Complex types", "Chaining"]);
+        assert_eq!(
+            headings(&notes.terms[2]),
+            vec!["Bounds", "Complex types", "Chaining"]
+        );
+        assert!(!notes.terms[2].code);
+        assert!(notes.glossary().contains(
+            "\n## Generic impls\n\n\
+             - [Complex types](advanced.md#complex-types) in *Rust Traits - Advanced*\n\
+             - [Chaining](nested/functional.md#chaining) in *Rust Traits - Functional*\n"
+        ));
+    }
+
+    #[test]
+    fn writes_see_also_blocks_with_relative_links() {
+        let notes = notes();
+        assert_eq!(
+            notes.see_also("nested/functional.md").unwrap(),
+            format!(
+                "{SEE_ALSO_START}\n## See also\n\n\
+                 - [Bounds](../basic.md#bounds) in *Rust Traits - Basic*: Trait bounds\n\
+                 - [Own types](../basic.md#own-types) in *Rust Traits - Basic*: `Meters`\n\
+                 - [Complex types](../advanced.md#complex-types) in *Rust Traits - Advanced*: \
+                 Generic impls, Trait bounds\n\
+                 \n{SEE_ALSO_END}\n"
+            )
+        );
+        assert!(notes.glossary().contains(
+            "\n## `Meters`\n\n- [Own types](basic.md#own-types) in *Rust Traits - Basic*\n\
+             - [Printing](nested/functional.md#printing) in *Rust Traits - Functional*\n"
+        ));
+    }
+
+    #[test]
+    fn replaces_see_also_blocks() {
+        let block = format!("{SEE_ALSO_START}\nnew\n{SEE_ALSO_END}\n");
+        let source = format!("# T\n\ntext\n\n{SEE_ALSO_START}\nold\n{SEE_ALSO_END}\n");
+        assert_eq!(
+            with_see_also(&source, Some(block.clone())),
+            format!("# T\n\ntext\n\n{block}")
+        );
+        assert_eq!(with_see_also(&source, None), "# T\n\ntext\n");
+        assert_eq!(with_see_also("# T\n\ntext", None), "# T\n\ntext");
+        assert_eq!(without_see_also(&source), "# T\n\ntext\n");
+    }
+
+    #[test]
+    fn reports_links_to_missing_files_and_headings() {
+        let dir = std::env::temp_dir().join(format!("notes-xref-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(
+            dir.join("README.md"),
+            "See [a](a.md) and [gone](gone.md).\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("a.md"),
+            "# A\n\n## Kept\n\n[ok](#kept), [b](nested/b.md#renamed)\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("nested/b.md"),
+            "# B\n\n## Current\n\n[up](../a.md#kept)\n",
+        )
+        .unwrap();
+
+        let broken: Vec<String> = broken_links(&dir)
+            .unwrap()
+            .iter()
+            .map(|link| format!("{}:{} {}", link.line, link.target, link.message))
+            .collect();
+        assert_eq!(
+            broken,
+            vec![
+                "1:gone.md no such file",
+                "5:nested/b.md#renamed no heading `#renamed` in nested/b.md",
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+//! Checks the real chapters.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use notes::search::Index;
+use notes::{check, include, lint, site, snippet, xref};
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+fn chapters() -> Vec<PathBuf> {
+    let root = root();
+    let chapters = snippet::chapters(&root).unwrap();
+    assert!(
+        !chapters.is_empty(),
+        "no chapters found under {}",
+        root.display()
+    );
+    chapters
+}
+
+#[test]
+fn every_include_directive_resolves() {
+    let mut broken = String::new();
+    for chapter in chapters() {
+        let source = fs::read_to_string(&chapter).unwrap();
+        if let Err(errors) = include::expand(&chapter, &source) {
+            for error in errors {
+                broken.push_str(&format!("{error}\n"));
+            }
+        }
+    }
+    assert!(broken.is_empty(), "{broken}");
+}
+
+#[test]
+fn every_chapter_snippet_meets_its_expectation() {
+    let mut failures = String::new();
+    for chapter in chapters() {
+        for outcome in check::check_chapter(&chapter).unwrap() {
+            if !outcome.passed() && !outcome.ignored() {
+                failures.push_str(&outcome.to_string());
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{failures}");
+}
+
+#[test]
+fn every_site_link_resolves() {
+    let pages = site::build(&root()).unwrap();
+    assert_eq!(pages.len(), chapters().len() + 1);
+    let mut broken = String::new();
+    for page in &pages {
+        let dir = page.path.parent().unwrap_or(Path::new(""));
+        for href in page.html.split("href=\"").skip(1) {
+            let href = &href[..href.find('"').unwrap()];
+            if href.contains("://") {
+                continue;
+            }
+            let (path, fragment) = href.split_once('#').unwrap_or((href, ""));
+            let target = match path {
+                "" => Some(page),
+                path => {
+                    let target = normalize(&dir.join(path));
+                    pages.iter().find(|page| page.path == target)
+                }
+            };
+            let resolved = match (target, path) {
+                (_, "style.css" | "../style.css") => true,
+                (Some(target), _) => {
+                    fragment.is_empty() || target.html.contains(&format!("id=\"{fragment}\""))
+                }
+                (None, _) => false,
+            };
+            if !resolved {
+                broken.push_str(&format!("{}: {href}\n", page.path.display()));
+            }
+        }
+    }
+    assert!(broken.is_empty(), "{broken}");
+}
+
+#[test]
+fn symbol_search_finds_the_sections_that_cover_a_trait() {
+    let index = Index::build(&root()).unwrap();
+    let hits = index.symbol("FromStr");
+    assert_eq!(hits[0].section.chapter, "traits-intermediate.md");
+    assert_eq!(hits[0].section.heading, "FromStr Trait");
+
+    let hits = index.symbol("and_then");
+    assert!(!hits.is_empty());
+    assert!(hits
+        .iter()
+        .all(|hit| hit.section.chapter == "traits-functional/MonadicResult.md"));
+}
+
+#[test]
+fn every_search_section_links_to_a_heading_of_the_site() {
+    let pages = site::build(&root()).unwrap();
+    for section in Index::build(&root()).unwrap().sections {
+        let page = pages
+            .iter()
+            .find(|page| page.path == Path::new(&section.page()))
+            .unwrap_or_else(|| panic!("no page for {}", section.chapter));
+        assert!(
+            section.id.is_empty() || page.html.contains(&format!("id=\"{}\"", section.id)),
+            "{} has no heading #{}",
+            section.page(),
+            section.id
+        );
+    }
+}
+
+#[test]
+fn cross_references_are_up_to_date_and_resolve() {
+    let references = xref::analyze(&root()).unwrap();
+    for (path, text) in references.generated(&root()).unwrap() {
+        assert!(
+            fs::read_to_string(&path).ok() == Some(text),
+            "{} is out of date, run `cargo run --bin notes-xref`",
+            path.display()
+        );
+    }
+    let broken = xref::broken_links(&root()).unwrap();
+    assert!(broken.is_empty(), "{broken:?}");
+}
+
+#[test]
+fn every_chapter_follows_the_conventions() {
+    let lints: Vec<String> = lint::lint(&root())
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert!(lints.is_empty(), "{}", lints.join("\n"));
+}
+
+/// Resolves `..` components of a relative path.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for part in path.components() {
+        match part {
+            std::path::Component::ParentDir => {
+                normal.pop();
+            }
+            part => normal.push(part),
+        }
+    }
+    normal
+}